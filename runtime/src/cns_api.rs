@@ -0,0 +1,33 @@
+//! Runtime API for querying the CNS registry from the node.
+
+use client::decl_runtime_apis;
+use rstd::prelude::*;
+
+use crate::{AccountId, AssetId, Balance, BlockNumber, Item, ItemId};
+use crate::domain_service::{BidInfo, DomainDetail, DomainName};
+
+decl_runtime_apis! {
+	/// Name resolution and registry lookups. Names are normalised, invalid names resolve to nothing.
+	pub trait DomainServiceApi {
+		/// The account `name` resolves to through its address, if it is active.
+		fn resolve(name: DomainName) -> Option<AccountId>;
+		/// The registration of `name`, expired or not.
+		fn domain_detail(name: DomainName) -> Option<DomainDetail<AccountId, BlockNumber>>;
+		/// Up to `count` names owned by `account`, starting from position `start` of its owner index.
+		fn names_of(account: AccountId, start: u64, count: u64) -> Vec<DomainName>;
+		/// The running auction for `name`.
+		fn bid_of(name: DomainName) -> Option<BidInfo<AccountId, Balance, BlockNumber>>;
+		/// Whether `name` can be bid on.
+		fn is_available(name: DomainName) -> bool;
+		/// The primary name of `account`, if it still resolves to them.
+		fn reverse(account: AccountId) -> Option<DomainName>;
+		/// All running auctions.
+		fn auctions() -> Vec<(DomainName, BidInfo<AccountId, Balance, BlockNumber>)>;
+	}
+
+	/// Storefront lookups.
+	pub trait XPayApi {
+		/// The items sold under `name` as `(item_id, item, quantity, (price_asset_id, price_amount))`.
+		fn items_of(name: DomainName) -> Vec<(ItemId, Item, u32, (AssetId, Balance))>;
+	}
+}
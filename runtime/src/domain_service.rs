@@ -8,52 +8,314 @@
 /// For more guidance on Substrate modules, see the example module
 /// https://github.com/paritytech/substrate/blob/master/srml/example/src/lib.rs
 
-use support::{decl_module, decl_storage, decl_event, StorageValue, StorageMap, dispatch::Result};
-use system::ensure_signed;
+use support::{decl_module, decl_storage, decl_event, StorageValue, StorageMap, dispatch::Result, ensure};
+use support::storage::unhashed;
+use system::{ensure_signed, ensure_root};
 use parity_codec::{Decode, Encode};
-use runtime_primitives::traits::As;
+use runtime_primitives::traits::{As, Bounded, CheckedAdd, CheckedMul, Hash, Zero};
+use runtime_primitives::Permill;
+use primitives::crypto::UncheckedFrom;
+use rstd::{marker::PhantomData, result};
+use runtime_io::blake2_256;
+#[cfg(feature = "std")]
+use runtime_io::with_storage;
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
 use rstd::prelude::*;
 
 /// The module's configuration trait.
-pub trait Trait: system::Trait {
+pub trait Trait: generic_asset::Trait + cennzx_spot::Trait {
 	// TODO: Add other types and constants required configure this module.
 
 	/// The overarching event type.
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+
+	/// Generates the account auction proceeds and renewal fees are pooled in.
+	type PoolAddressGenerator: PoolAddressFor<Self::AccountId>;
+
+	/// Notified of domains removed from the registry, so data kept for them elsewhere can be dropped.
+	type OnDomainRemoved: OnDomainRemoved<Self::Hash>;
+
+	/// Notified of leased domains returning to their lessor, so data the lessee kept for them
+	/// elsewhere can be dropped.
+	type OnLeaseEnded: OnLeaseEnded<Self::Hash, Self::AccountId>;
+}
+
+/// Determines the account holding the revenue pool.
+pub trait PoolAddressFor<AccountId> {
+	fn pool_address() -> AccountId;
+}
+
+/// Derives the revenue pool account from a fixed seed.
+pub struct PoolAddressGenerator<T: Trait>(PhantomData<T>);
+
+impl<T: Trait> PoolAddressFor<T::AccountId> for PoolAddressGenerator<T>
+where
+	T::AccountId: UncheckedFrom<T::Hash>,
+{
+	fn pool_address() -> T::AccountId {
+		T::AccountId::unchecked_from(T::Hashing::hash(b"cns:revenue-pool"))
+	}
+}
+
+/// Handles the removal of a domain from the registry, by release, revocation or expiry of its parent.
+pub trait OnDomainRemoved<Hash> {
+	fn on_domain_removed(node: &Hash);
+}
+
+impl<Hash> OnDomainRemoved<Hash> for () {
+	fn on_domain_removed(_: &Hash) {}
+}
+
+/// Handles the end of the lease of a domain to `lessee`, when the domain returns to its lessor.
+pub trait OnLeaseEnded<Hash, AccountId> {
+	fn on_lease_ended(node: &Hash, lessee: &AccountId);
+}
+
+impl<Hash, AccountId> OnLeaseEnded<Hash, AccountId> for () {
+	fn on_lease_ended(_: &Hash, _: &AccountId) {}
 }
 
 pub type DomainAddr = Vec<u8>;
 pub type DomainName = Vec<u8>;
 
-#[cfg_attr(feature = "std", derive(Debug))]
+pub type BalanceOf<T> = <T as generic_asset::Trait>::Balance;
+pub type AssetIdOf<T> = <T as generic_asset::Trait>::AssetId;
+
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
 #[derive(Clone, Encode, Decode, Default, PartialEq, Eq)]
 pub struct DomainDetail<AccountId, BlockNumber> {
 	pub owner: AccountId,
+	/// Expiry of the registration. A subdomain also expires with its parent.
 	pub expire: BlockNumber,
 	pub addr: Option<DomainAddr>,
+	/// The domain this one is a subdomain of.
+	pub parent: Option<DomainName>,
 }
 
-#[cfg_attr(feature = "std", derive(Debug))]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
 #[derive(Clone, Encode, Decode, Default, PartialEq, Eq)]
-pub struct Bid<AccountId> {
+pub struct Bid<AccountId, Balance> {
 	pub bidder: AccountId,
-	pub name: DomainName,
-	pub amount: u128,
+	/// Amount of the settlement asset held in reserve for this bid.
+	pub amount: Balance,
+}
+
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Default, PartialEq, Eq)]
+pub struct BidInfo<AccountId, Balance, BlockNumber> {
+	/// The highest bid, or for sealed auctions the highest revealed bid (zero amount until a reveal).
+	pub bid: Bid<AccountId, Balance>,
+	pub end: BlockNumber,
+	/// The latest block an open auction can be extended to by bids near its end.
+	pub latest_end: BlockNumber,
+	/// Present if this is a sealed-bid auction.
+	pub sealed: Option<SealedBidInfo<Balance, BlockNumber>>,
 }
 
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Default, PartialEq, Eq)]
+pub struct SealedBidInfo<Balance, BlockNumber> {
+	/// Last block in which bids can be committed. Reveals are accepted after it until `BidInfo::end`.
+	pub commit_end: BlockNumber,
+	/// The second highest revealed amount, which is what the winner pays.
+	pub second_price: Balance,
+}
+
+/// A restriction on registering a name, managed by root.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Encode, Decode, PartialEq, Eq)]
+pub enum NameRestriction<AccountId> {
+	/// The name can only be assigned by root, to the given account if any.
+	Reserved(Option<AccountId>),
+	/// The name can never be registered.
+	Blocked,
+}
+
+/// A resolver record together with the deposit reserved for storing it.
 #[cfg_attr(feature = "std", derive(Debug))]
 #[derive(Clone, Encode, Decode, Default, PartialEq, Eq)]
-pub struct BidInfo<AccountId, BlockNumber> {
-	pub bid: Bid<AccountId>,
+pub struct Record<Value, AccountId, Balance> {
+	pub value: Value,
+	/// The account the deposit is returned to when the record is cleared.
+	pub depositor: AccountId,
+	pub deposit: Balance,
+}
+
+/// A transfer proposed by the owner of a domain, completed when the recipient accepts it.
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Default, PartialEq, Eq)]
+pub struct PendingTransfer<AccountId, BlockNumber> {
+	/// The owner at the time of the proposal. The proposal lapses if the domain changes hands.
+	pub from: AccountId,
+	pub to: AccountId,
+	/// Last block in which the transfer can be accepted, if limited.
+	pub expire: Option<BlockNumber>,
+}
+
+/// A domain offered for sale at a fixed price.
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Default, PartialEq, Eq)]
+pub struct Listing<AccountId, AssetId, Balance> {
+	/// The owner at the time of listing. The listing lapses if the domain changes hands.
+	pub seller: AccountId,
+	pub asset_id: AssetId,
+	pub price: Balance,
+}
+
+/// An offer to buy a domain, held in reserve from the buyer until accepted or withdrawn.
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Default, PartialEq, Eq)]
+pub struct Offer<AssetId, Balance> {
+	pub asset_id: AssetId,
+	pub amount: Balance,
+}
+
+/// A lease offered by the owner of a domain, started when the lessee accepts it.
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Default, PartialEq, Eq)]
+pub struct LeaseOffer<AccountId, AssetId, Balance, BlockNumber> {
+	/// The owner at the time of the offer. The offer lapses if the domain changes hands.
+	pub lessor: AccountId,
+	pub lessee: AccountId,
+	/// Number of blocks the lease runs for once accepted.
+	pub duration: BlockNumber,
+	pub asset_id: AssetId,
+	pub price: Balance,
+}
+
+/// A running lease. The lessee owns the domain until block `end`, in which it returns to the lessor.
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[derive(Clone, Encode, Decode, Default, PartialEq, Eq)]
+pub struct Lease<AccountId, BlockNumber> {
+	pub lessor: AccountId,
+	pub lessee: AccountId,
 	pub end: BlockNumber,
+	/// Address of the domain when the lease started, restored at its end.
+	pub addr: Option<DomainAddr>,
 }
 
+pub type LeaseOfferOf<T> = LeaseOffer<<T as system::Trait>::AccountId, AssetIdOf<T>, BalanceOf<T>, <T as system::Trait>::BlockNumber>;
+
+pub type RecordOf<T, Value> = Record<Value, <T as system::Trait>::AccountId, BalanceOf<T>>;
+
+/// Separates the labels of a subdomain name, e.g. `shop.acme`.
+pub const LABEL_SEPARATOR: u8 = b'.';
+
+/// Maximum length of a name in bytes, separators included.
+pub const MAX_NAME_LENGTH: usize = 253;
+/// Maximum length of a single label in bytes.
+pub const MAX_LABEL_LENGTH: usize = 63;
+
+/// Maximum length in bytes of a text record key or value, or of a content hash.
+pub const MAX_RECORD_LENGTH: usize = 1024;
+
 // This module's storage items.
 decl_storage! {
 	trait Store for Module<T: Trait> as DomainService {
-		Domains get(domains): map DomainName => Option<DomainDetail<T::AccountId, T::BlockNumber>>;
-		Owners get(owners): map T::AccountId => Option<Vec<DomainName>>;
-		Bids get(bids): map DomainName => Option<BidInfo<T::AccountId, T::BlockNumber>>;
+		/// Registered domains by name hash, see `namehash`. Storage of other per-name data is keyed the same way.
+		Domains get(domains): map T::Hash => Option<DomainDetail<T::AccountId, T::BlockNumber>>;
+		/// Plain names of registered domains and of names in auction.
+		Names get(name_of): map T::Hash => Option<DomainName>;
+		/// Domains owned by an account, at positions `0..OwnedDomainsCount`.
+		OwnedDomains get(owned_domain): map (T::AccountId, u64) => Option<T::Hash>;
+		OwnedDomainsCount get(owned_domains_count): map T::AccountId => u64;
+		/// Position of a domain in its owner's `OwnedDomains`.
+		OwnedDomainsIndex: map T::Hash => u64;
+		/// Subdomains directly under a domain.
+		Subdomains get(subdomains): map T::Hash => Vec<T::Hash>;
+		/// Text records of a domain by key, e.g. `email`, `url` or `avatar`.
+		TextRecords get(text_record): map (T::Hash, Vec<u8>) => Option<RecordOf<T, Vec<u8>>>;
+		/// Keys of the text records set for a domain.
+		TextRecordKeys get(text_record_keys): map T::Hash => Vec<Vec<u8>>;
+		/// Content hash record of a domain, e.g. an IPFS or Swarm hash.
+		ContentHashes get(content_hash): map T::Hash => Option<RecordOf<T, Vec<u8>>>;
+		/// Receiving address of a domain for a specific asset, taking precedence over `DomainDetail::addr`.
+		AssetAddresses get(asset_address): map (T::Hash, AssetIdOf<T>) => Option<RecordOf<T, T::AccountId>>;
+		/// Assets with a receiving address set for a domain.
+		AssetAddressAssets get(asset_address_assets): map T::Hash => Vec<AssetIdOf<T>>;
+		/// Names that cannot be auctioned.
+		Restrictions get(restriction): map T::Hash => Option<NameRestriction<T::AccountId>>;
+		/// Account approved to manage and transfer a domain on behalf of its owner.
+		Approvals get(approved): map T::Hash => Option<T::AccountId>;
+		/// Whether an account is an operator of all domains of an owner, as `(owner, operator)`.
+		Operators get(is_operator): map (T::AccountId, T::AccountId) => bool;
+		/// Last block of the owner lock of a domain, see `lock`.
+		Locks get(locked_until): map T::Hash => Option<T::BlockNumber>;
+		/// Domains frozen by root, e.g. while under dispute.
+		Frozen get(is_frozen): map T::Hash => bool;
+		/// Transfers waiting to be accepted by their recipient.
+		PendingTransfers get(pending_transfer): map T::Hash => Option<PendingTransfer<T::AccountId, T::BlockNumber>>;
+		/// Domains for sale.
+		Listings get(listing): map T::Hash => Option<Listing<T::AccountId, AssetIdOf<T>, BalanceOf<T>>>;
+		/// Offers to buy a domain by buyer.
+		Offers get(offer): map (T::Hash, T::AccountId) => Option<Offer<AssetIdOf<T>, BalanceOf<T>>>;
+		/// Leases waiting to be accepted by their lessee.
+		LeaseOffers get(lease_offer): map T::Hash => Option<LeaseOfferOf<T>>;
+		/// Leased domains.
+		Leases get(lease): map T::Hash => Option<Lease<T::AccountId, T::BlockNumber>>;
+		/// Leased domains returned to their lessor at the start of the given block.
+		LeaseEnds get(lease_ends): map T::BlockNumber => Vec<T::Hash>;
+		/// The name an account chose to be displayed as, see `reverse`.
+		PrimaryNames get(primary_name): map T::AccountId => Option<DomainName>;
+		Bids get(bids): map T::Hash => Option<BidInfo<T::AccountId, BalanceOf<T>, T::BlockNumber>>;
+		/// Name hashes of the running auctions, see `auctions`.
+		RunningAuctions get(running_auctions): Vec<T::Hash>;
+		/// Names whose auction is settled at the end of the given block.
+		AuctionEnds get(auction_ends): map T::BlockNumber => Vec<DomainName>;
+		/// Sealed bid commitments as `(hash of (bidder, name, amount, salt), reserved deposit)`.
+		Commitments get(commitment): map (T::Hash, T::AccountId) => Option<(T::Hash, BalanceOf<T>)>;
+		/// Accounts that committed a bid in the sealed auction for a name.
+		Committers get(committers): map T::Hash => Vec<T::AccountId>;
+		/// Names to check for release at the end of the given block.
+		Expiries get(expiries): map T::BlockNumber => Vec<DomainName>;
+
+		/// Number of blocks an open auction runs for.
+		AuctionPeriod get(auction_period) config(): T::BlockNumber;
+		/// A bid within this many blocks of the end of an open auction extends it.
+		ExtensionWindow get(extension_window) config(): T::BlockNumber;
+		/// Number of blocks an open auction is extended by, see `ExtensionWindow`.
+		ExtensionPeriod get(extension_period) config(): T::BlockNumber;
+		/// Maximum number of blocks an open auction can be extended by in total.
+		MaxExtension get(max_extension) config(): T::BlockNumber;
+		/// Number of blocks bids can be committed for in a sealed auction.
+		CommitPeriod get(commit_period) config(): T::BlockNumber;
+		/// Number of blocks committed bids can be revealed for in a sealed auction.
+		RevealPeriod get(reveal_period) config(): T::BlockNumber;
+		/// Lowest amount accepted for a bid. Also the price a sealed auction with a single revealed bid
+		/// is settled at.
+		MinimumBid get(minimum_bid) config(): BalanceOf<T>;
+		/// Amount by which a bid must exceed the current highest bid of an open auction.
+		MinimumBidIncrement get(minimum_bid_increment) config(): BalanceOf<T>;
+		/// Number of blocks a domain is registered or renewed for.
+		RegistrationPeriod get(registration_period) config(): T::BlockNumber;
+		/// Number of blocks an expired domain is held for its owner before it can be auctioned again.
+		GracePeriod get(grace_period) config(): T::BlockNumber;
+		/// Renewal price per registration period by name length. Entry `i` applies to names of `i + 1`
+		/// bytes and the last entry to all longer names.
+		PriceSchedule get(price_schedule) config(): Vec<BalanceOf<T>>;
+		/// Amount of the settlement asset reserved for each record set.
+		RecordDeposit get(record_deposit) config(): BalanceOf<T>;
+		/// Share of auction proceeds and renewal fees burned instead of added to the revenue pool.
+		BurnShare get(burn_share) config(): Permill;
+	}
+	add_extra_genesis {
+		// Names registered at genesis as `(name, owner, addr, expire)`.
+		config(domains): Vec<(DomainName, T::AccountId, Option<DomainAddr>, T::BlockNumber)>;
+		config(reserved_names): Vec<DomainName>;
+
+		build(|storage: &mut runtime_primitives::StorageOverlay, _: &mut runtime_primitives::ChildrenStorageOverlay, config: &GenesisConfig<T>| {
+			with_storage(storage, || {
+				for (name, owner, addr, expire) in &config.domains {
+					let name = normalize_name(name).expect("genesis domain names are valid; qed");
+					<Module<T>>::register(&name, owner.clone(), *expire, addr.clone());
+				}
+				for name in &config.reserved_names {
+					let (_, node) = <Module<T>>::hash_name(name).expect("genesis reserved names are valid; qed");
+					<Restrictions<T>>::insert(node, NameRestriction::Reserved(None));
+				}
+			});
+		});
 	}
 }
 
@@ -64,104 +326,2709 @@ decl_module! {
 		// this is needed only if you are using events in your module
 		fn deposit_event<T>() = default;
 
-		pub fn bid(origin, name: DomainName, amount: u128) -> Result {
+		fn on_initialize(n: T::BlockNumber) {
+			for node in <LeaseEnds<T>>::take(n) {
+				Self::end_lease(&node, n);
+			}
+		}
+
+		fn on_finalize(n: T::BlockNumber) {
+			for name in <AuctionEnds<T>>::take(n) {
+				Self::settle_auction(name, n);
+			}
+			for name in <Expiries<T>>::take(n) {
+				Self::release_expired(name, n);
+			}
+		}
+
+		pub fn bid(origin, name: DomainName, amount: BalanceOf<T>) -> Result {
 			let who = ensure_signed(origin)?;
+			Self::place_bid(who, name, amount, None)
+		}
 
-			if let Some(bid_info) = <Bids<T>>::get(name.clone()) {
-				if amount <= bid_info.bid.amount {
-					return Err("bid amount too small")
-				}
+		/// Bid `amount` of the settlement asset on `name`, paying with `paying_asset_id`. Up to
+		/// `max_paying_amount` of it is converted through CENNZX-Spot, the converted amount is then held
+		/// in reserve like any other bid and refunded in the settlement asset when outbid.
+		pub fn bid_with_asset(
+			origin,
+			name: DomainName,
+			amount: BalanceOf<T>,
+			paying_asset_id: AssetIdOf<T>,
+			max_paying_amount: BalanceOf<T>
+		) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::place_bid(who, name, amount, Some((paying_asset_id, max_paying_amount)))
+		}
+
+		/// Commit to a sealed bid on `name`, starting a sealed auction if there is none yet.
+		/// `commitment` is the hash of the encoded `(bidder, name, amount, salt)`, using the normalised
+		/// name, so that only the bidder can reveal it. `deposit` is reserved until the bid is revealed,
+		/// must cover the amount and is forfeited if the bid is never revealed. It must be at least the
+		/// minimum bid.
+		pub fn commit_bid(origin, name: DomainName, commitment: T::Hash, deposit: BalanceOf<T>) -> Result {
+			let who = ensure_signed(origin)?;
+			ensure!(!Self::awaits_migration(&name), "domain awaits migration");
+			let (name, node) = Self::hash_name(&name)?;
+
+			ensure!(!name.contains(&LABEL_SEPARATOR), "subdomains cannot be auctioned");
+			ensure!(!Self::awaits_migration(&name), "domain awaits migration");
+			ensure!(!<Domains<T>>::exists(&node), "domain already registered");
+			ensure!(Self::restriction(&node).is_none(), "domain is reserved or blocked");
+			ensure!(!<Commitments<T>>::exists((node, who.clone())), "bid already committed");
+			ensure!(deposit >= Self::minimum_bid(), "deposit too small");
+
+			let now = <system::Module<T>>::block_number();
+
+			let (bid_info, started) = if let Some(bid_info) = <Bids<T>>::get(&node) {
+				let sealed = bid_info.sealed.as_ref().ok_or_else(|| "domain is in an open auction")?;
+				ensure!(now <= sealed.commit_end, "commit phase is over");
+				(bid_info, false)
+			} else {
+				let commit_end = now + Self::commit_period();
+				let end = commit_end + Self::reveal_period();
+
+				(BidInfo {
+					bid: Bid {
+						bidder: Default::default(),
+						amount: Zero::zero(),
+					},
+					end: end,
+					latest_end: end,
+					sealed: Some(SealedBidInfo {
+						commit_end: commit_end,
+						second_price: Zero::zero(),
+					}),
+				}, true)
+			};
+
+			<generic_asset::Module<T>>::reserve(&Self::settlement_asset_id(), &who, deposit)?;
+
+			if started {
+				<AuctionEnds<T>>::mutate(bid_info.end, |names| names.push(name.clone()));
+				<Names<T>>::insert(&node, &name);
+				<RunningAuctions<T>>::mutate(|nodes| nodes.push(node));
 			}
+			<Commitments<T>>::insert((node, who.clone()), (commitment, deposit));
+			<Committers<T>>::mutate(&node, |committers| committers.push(who.clone()));
+			<Bids<T>>::insert(&node, bid_info);
+
+			Self::deposit_event(RawEvent::BidCommitted(who, name));
+
+			Ok(())
+		}
+
+		/// Reveal a sealed bid committed with `commit_bid`. Losing bids are refunded in full, the
+		/// highest bid keeps `amount` reserved until the auction is settled.
+		pub fn reveal_bid(origin, name: DomainName, amount: BalanceOf<T>, salt: T::Hash) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let mut bid_info = <Bids<T>>::get(&node).ok_or_else(|| "no auction for domain")?;
+			let mut sealed = bid_info.sealed.clone().ok_or_else(|| "domain is in an open auction")?;
 
 			let now = <system::Module<T>>::block_number();
+			ensure!(now > sealed.commit_end, "reveal phase has not started");
 
-			<Bids<T>>::insert(name.clone(), BidInfo {
-				bid: Bid {
+			let (commitment, deposit) = <Commitments<T>>::get((node, who.clone()))
+				.ok_or_else(|| "no bid committed")?;
+			ensure!(T::Hashing::hash_of(&(&who, &name, &amount, &salt)) == commitment, "bid does not match commitment");
+			ensure!(!amount.is_zero() && amount >= Self::minimum_bid(), "bid amount too small");
+			ensure!(amount <= deposit, "deposit does not cover bid");
+
+			<Commitments<T>>::remove((node, who.clone()));
+
+			let asset_id = Self::settlement_asset_id();
+
+			Self::deposit_event(RawEvent::BidRevealed(who.clone(), name.clone(), amount));
+
+			if amount > bid_info.bid.amount {
+				if !bid_info.bid.amount.is_zero() {
+					<generic_asset::Module<T>>::unreserve(&asset_id, &bid_info.bid.bidder, bid_info.bid.amount);
+					Self::deposit_event(RawEvent::Outbid(bid_info.bid.bidder.clone(), name.clone(), bid_info.bid.amount));
+				}
+				<generic_asset::Module<T>>::unreserve(&asset_id, &who, deposit - amount);
+
+				sealed.second_price = bid_info.bid.amount;
+				bid_info.bid = Bid {
 					bidder: who,
-					name: name,
 					amount: amount,
-				},
-				end: add_block_number_by(now, 10),
-			});
+				};
+			} else {
+				if amount > sealed.second_price {
+					sealed.second_price = amount;
+				}
+				<generic_asset::Module<T>>::unreserve(&asset_id, &who, deposit);
+			}
+
+			bid_info.sealed = Some(sealed);
+			<Bids<T>>::insert(node, bid_info);
 
 			Ok(())
 		}
 
 		pub fn update(origin, name: DomainName, addr: Option<DomainAddr>) -> Result {
-			let domain_detail_record = {
-				if let Some(bid_info) = <Bids<T>>::take(name.clone()) {
-					//TODO: transfer money to pool
-					Some(DomainDetail {
-						owner: bid_info.bid.bidder,
-						expire: add_block_number_by(bid_info.end, 1000),
-						addr: None,
-					})
-				} else {
-					<Domains<T>>::get(name.clone())
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let mut domain_detail = Self::ensure_controller(&who, &node)?;
+			Self::ensure_unlocked(&node)?;
+
+			domain_detail.addr = addr.clone();
+			<Domains<T>>::insert(node, domain_detail);
+
+			Self::deposit_event(RawEvent::AddressUpdated(name, addr));
+
+			Ok(())
+		}
+
+		pub fn transfer(origin, name: DomainName, to: T::AccountId) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let domain_detail = <Domains<T>>::get(&node).ok_or_else(|| "domain does not exist")?;
+			if who != domain_detail.owner {
+				return Err("does not own this domain")
+			}
+			Self::ensure_not_expired(&domain_detail)?;
+			Self::ensure_unlocked(&node)?;
+			Self::ensure_not_leased(&node)?;
+
+			Self::set_owner(&node, domain_detail, to.clone());
+
+			Self::deposit_event(RawEvent::DomainTransferred(name, who, to));
+
+			Ok(())
+		}
+
+		/// Lock `name` until the end of block `until`, or its expiry if earlier: its address and records
+		/// cannot be changed and it cannot be transferred. A lock can be extended but not shortened. The
+		/// owner of the parent domain can still reassign or revoke a locked subdomain.
+		pub fn lock(origin, name: DomainName, until: T::BlockNumber) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let domain_detail = Self::ensure_owner(&who, &node)?;
+			Self::ensure_not_leased(&node)?;
+			ensure!(until >= <system::Module<T>>::block_number(), "lock ends in the past");
+			let until = until.min(Self::effective_expire(&domain_detail));
+			if let Some(locked_until) = Self::locked_until(&node) {
+				ensure!(until > locked_until, "lock can only be extended");
+			}
+
+			<Locks<T>>::insert(&node, until);
+
+			Self::deposit_event(RawEvent::DomainLocked(name, until));
+
+			Ok(())
+		}
+
+		/// Propose to transfer `name` to `to`, who has to accept it with `accept_transfer`, until the end
+		/// of block `expire` if given. Replaces any previous proposal for the domain.
+		pub fn propose_transfer(origin, name: DomainName, to: T::AccountId, expire: Option<T::BlockNumber>) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			Self::ensure_owner(&who, &node)?;
+			Self::ensure_not_leased(&node)?;
+			ensure!(who != to, "cannot transfer to self");
+			if let Some(expire) = expire {
+				ensure!(expire >= <system::Module<T>>::block_number(), "expiry is in the past");
+			}
+
+			<PendingTransfers<T>>::insert(&node, PendingTransfer {
+				from: who.clone(),
+				to: to.clone(),
+				expire: expire,
+			});
+
+			Self::deposit_event(RawEvent::TransferProposed(name, who, to, expire));
+
+			Ok(())
+		}
+
+		/// Accept the transfer of `name` proposed to the sender.
+		pub fn accept_transfer(origin, name: DomainName) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let pending = Self::pending_transfer(&node).ok_or_else(|| "no pending transfer")?;
+			ensure!(pending.to == who, "transfer not proposed to sender");
+			if let Some(expire) = pending.expire {
+				ensure!(<system::Module<T>>::block_number() <= expire, "transfer proposal expired");
+			}
+
+			let domain_detail = Self::active_domain(&node).ok_or_else(|| "domain does not exist")?;
+			ensure!(domain_detail.owner == pending.from, "transfer proposal has lapsed");
+			Self::ensure_unlocked(&node)?;
+
+			Self::set_owner(&node, domain_detail, who.clone());
+
+			Self::deposit_event(RawEvent::DomainTransferred(name, pending.from, who));
+
+			Ok(())
+		}
+
+		/// Withdraw the pending transfer of `name`. Callable by the proposing owner or the recipient.
+		pub fn cancel_transfer(origin, name: DomainName) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let pending = Self::pending_transfer(&node).ok_or_else(|| "no pending transfer")?;
+			ensure!(who == pending.from || who == pending.to, "not party to transfer");
+
+			<PendingTransfers<T>>::remove(&node);
+
+			Self::deposit_event(RawEvent::TransferCancelled(name));
+
+			Ok(())
+		}
+
+		/// Approve `approved` to manage and transfer `name`, or clear the approval (`None`). Callable
+		/// by the owner or an operator of the owner. The approval is cleared when the domain changes hands.
+		pub fn approve(origin, name: DomainName, approved: Option<T::AccountId>) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let domain_detail = <Domains<T>>::get(&node).ok_or_else(|| "domain does not exist")?;
+			if who != domain_detail.owner && !Self::is_operator((domain_detail.owner.clone(), who)) {
+				return Err("not owner or operator")
+			}
+			Self::ensure_not_expired(&domain_detail)?;
+
+			match approved {
+				Some(ref approved) => <Approvals<T>>::insert(&node, approved),
+				None => <Approvals<T>>::remove(&node),
+			}
+
+			Self::deposit_event(RawEvent::Approval(name, domain_detail.owner, approved));
+
+			Ok(())
+		}
+
+		/// Allow or disallow `operator` to manage and transfer all domains of the sender.
+		pub fn set_approval_for_all(origin, operator: T::AccountId, approved: bool) -> Result {
+			let who = ensure_signed(origin)?;
+			ensure!(who != operator, "cannot approve self");
+
+			if approved {
+				<Operators<T>>::insert((who.clone(), operator.clone()), true);
+			} else {
+				<Operators<T>>::remove((who.clone(), operator.clone()));
+			}
+
+			Self::deposit_event(RawEvent::ApprovalForAll(who, operator, approved));
+
+			Ok(())
+		}
+
+		/// Transfer `name` to `to` on behalf of its owner. Callable by the owner, the account approved
+		/// for the domain or an operator of the owner.
+		pub fn transfer_from(origin, name: DomainName, to: T::AccountId) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let domain_detail = Self::ensure_controller(&who, &node)?;
+			Self::ensure_unlocked(&node)?;
+			Self::ensure_not_leased(&node)?;
+			let from = domain_detail.owner.clone();
+
+			Self::set_owner(&node, domain_detail, to.clone());
+
+			Self::deposit_event(RawEvent::DomainTransferred(name, from, to));
+
+			Ok(())
+		}
+
+		/// Set or clear (`None`) the text record `key` of `name`.
+		pub fn set_text(origin, name: DomainName, key: Vec<u8>, value: Option<Vec<u8>>) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			Self::ensure_controller(&who, &node)?;
+			Self::ensure_unlocked(&node)?;
+			ensure!(key.len() <= MAX_RECORD_LENGTH, "record key too long");
+
+			let record = match value {
+				Some(value) => {
+					ensure!(value.len() <= MAX_RECORD_LENGTH, "record too long");
+					Some(Self::new_record(&who, value)?)
 				}
+				None => None,
 			};
 
-			if let Some(domain_detail) = domain_detail_record {
-				let who = ensure_signed(origin)?;
-				if who != domain_detail.owner {
-					return Err("not owner")
+			let old_record = <TextRecords<T>>::take((node, key.clone()));
+			match record {
+				Some(ref record) => {
+					if old_record.is_none() {
+						<TextRecordKeys<T>>::mutate(&node, |keys| keys.push(key.clone()));
+					}
+					<TextRecords<T>>::insert((node, key.clone()), record);
+				}
+				None => <TextRecordKeys<T>>::mutate(&node, |keys| keys.retain(|k| *k != key)),
+			}
+			if let Some(old_record) = old_record {
+				Self::release_record(old_record);
+			}
+
+			Self::deposit_event(RawEvent::TextChanged(name, key, record.map(|r| r.value)));
+
+			Ok(())
+		}
+
+		/// Set or clear (`None`) the content hash record of `name`.
+		pub fn set_content_hash(origin, name: DomainName, hash: Option<Vec<u8>>) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			Self::ensure_controller(&who, &node)?;
+			Self::ensure_unlocked(&node)?;
+
+			let record = match hash {
+				Some(hash) => {
+					ensure!(hash.len() <= MAX_RECORD_LENGTH, "record too long");
+					Some(Self::new_record(&who, hash)?)
 				}
+				None => None,
+			};
+
+			let old_record = <ContentHashes<T>>::take(&node);
+			if let Some(ref record) = record {
+				<ContentHashes<T>>::insert(&node, record);
+			}
+			if let Some(old_record) = old_record {
+				Self::release_record(old_record);
+			}
+
+			Self::deposit_event(RawEvent::ContentHashChanged(name, record.map(|r| r.value)));
+
+			Ok(())
+		}
+
+		/// Set or clear (`None`) the account receiving `asset_id` sent to `name`.
+		pub fn set_asset_address(origin, name: DomainName, asset_id: AssetIdOf<T>, addr: Option<T::AccountId>) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			Self::ensure_controller(&who, &node)?;
+			Self::ensure_unlocked(&node)?;
+
+			let record = match addr {
+				Some(addr) => Some(Self::new_record(&who, addr)?),
+				None => None,
+			};
 
-				let owner_domains = if let Some(mut domain_names) = <Owners<T>>::take(who.clone()) {
-					if !domain_names.contains(&name) {
-						domain_names.push(name.clone());
+			let old_record = <AssetAddresses<T>>::take((node, asset_id.clone()));
+			match record {
+				Some(ref record) => {
+					if old_record.is_none() {
+						<AssetAddressAssets<T>>::mutate(&node, |assets| assets.push(asset_id.clone()));
 					}
-					domain_names
-				} else {
-					vec![name.clone()]
-				};
-				<Owners<T>>::insert(who.clone(), owner_domains);
+					<AssetAddresses<T>>::insert((node, asset_id.clone()), record);
+				}
+				None => <AssetAddressAssets<T>>::mutate(&node, |assets| assets.retain(|a| *a != asset_id)),
+			}
+			if let Some(old_record) = old_record {
+				Self::release_record(old_record);
+			}
 
-				<Domains<T>>::insert(name, DomainDetail {
-					owner: domain_detail.owner,
-					expire: domain_detail.expire,
-					addr: addr,
-				});
+			Self::deposit_event(RawEvent::AssetAddressChanged(name, asset_id, record.map(|r| r.value)));
 
-				Ok(())
-			} else {
-				Err("domain does not exist")
+			Ok(())
+		}
+
+		/// Set or clear (`None`) the primary name of the sender. The name must resolve to the sender.
+		pub fn set_primary_name(origin, name: Option<DomainName>) -> Result {
+			let who = ensure_signed(origin)?;
+
+			match name {
+				Some(name) => {
+					let (name, node) = Self::hash_name(&name)?;
+					ensure!(Self::resolve(&node) == Some(who.clone()), "domain does not resolve to sender");
+
+					<PrimaryNames<T>>::insert(&who, &name);
+					Self::deposit_event(RawEvent::PrimaryNameChanged(who, Some(name)));
+				}
+				None => {
+					<PrimaryNames<T>>::remove(&who);
+					Self::deposit_event(RawEvent::PrimaryNameChanged(who, None));
+				}
 			}
+
+			Ok(())
 		}
 
-		pub fn transfer(origin, name: DomainName, to: T::AccountId) -> Result {
+		/// Extend the registration of `name` by `periods` registration periods, charging the owner
+		/// according to the price schedule. Expired domains can be renewed during the grace period.
+		pub fn renew(origin, name: DomainName, periods: u32) -> Result {
 			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
 
-			let mut domain_names = <Owners<T>>::take(who.clone()).unwrap_or(vec![]);
-			if domain_names.is_empty() || !domain_names.contains(&name) {
-				return Err("does not own this domain")
+			ensure!(periods > 0, "no renewal periods");
+
+			// domains still in the registry are either active or in their grace period
+			let mut domain_detail = <Domains<T>>::get(&node).ok_or_else(|| "domain does not exist")?;
+			if who != domain_detail.owner {
+				return Err("not owner")
 			}
+			ensure!(domain_detail.parent.is_none(), "subdomains expire with their parent");
 
-			domain_names = domain_names.into_iter().filter(|n| *n != name).collect();
-			<Owners<T>>::insert(who.clone(), domain_names);
+			let price = Self::renewal_price(&name, periods)?;
+			let expire = Self::registration_period()
+				.checked_mul(&T::BlockNumber::sa(periods as u64))
+				.and_then(|extension| domain_detail.expire.checked_add(&extension))
+				.ok_or_else(|| "expiry overflow")?;
 
-			// could not fail
-			let old_record = <Domains<T>>::take(name.clone()).unwrap();
-			<Domains<T>>::insert(name, DomainDetail {
-				owner: to,
-				expire: old_record.expire,
-				addr: old_record.addr,
-			});
+			<generic_asset::Module<T>>::reserve(&Self::settlement_asset_id(), &who, price)?;
+			Self::collect_payment(&who, price);
+
+			domain_detail.expire = expire;
+			<Domains<T>>::insert(node, domain_detail);
+
+			Self::deposit_event(RawEvent::DomainRenewed(name, expire));
 
 			Ok(())
 		}
-	}
-}
 
-fn add_block_number_by<B: As<u64>>(block_number: B, by: u64) -> B {
-	B::sa(block_number.as_() + by)
-}
+		/// Create `label.parent` for `owner`. The subdomain expires with its parent, or at `expire` if
+		/// that is earlier.
+		pub fn create_subdomain(
+			origin,
+			parent: DomainName,
+			label: DomainName,
+			owner: T::AccountId,
+			expire: Option<T::BlockNumber>
+		) -> Result {
+			let who = ensure_signed(origin)?;
+			let (parent, parent_node) = Self::hash_name(&parent)?;
+			let label = normalize_name(&label)?;
+			ensure!(!label.contains(&LABEL_SEPARATOR), "subdomain label contains a separator");
 
-decl_event!(
-	pub enum Event<T> where AccountId = <T as system::Trait>::AccountId {
-		// Just a dummy event.
-		// Event `Something` is declared with a parameter of the type `u32` and `AccountId`
-		// To emit this event, we call the deposit funtion, from our runtime funtions
-		SomethingStored(u32, AccountId),
-	}
-);
+			let parent_detail = Self::active_domain(&parent_node).ok_or_else(|| "parent domain does not exist")?;
+			if who != parent_detail.owner {
+				return Err("not owner")
+			}
+			Self::ensure_not_leased(&parent_node)?;
+
+			let name = subdomain_name(&label, &parent);
+			ensure!(name.len() <= MAX_NAME_LENGTH, "domain name too long");
+			let node = Self::subnode(&parent_node, &label);
+			ensure!(!<Domains<T>>::exists(&node), "domain already registered");
+
+			let expire = Self::subdomain_expire(&parent_detail, expire)?;
+			let domain_detail = DomainDetail {
+				owner: owner.clone(),
+				expire: expire,
+				addr: None,
+				parent: Some(parent.clone()),
+			};
+
+			Self::add_owned_domain(&owner, &node);
+			Self::schedule_release(&name, &domain_detail);
+			<Domains<T>>::insert(&node, domain_detail);
+			<Names<T>>::insert(&node, &name);
+			<Subdomains<T>>::mutate(&parent_node, |nodes| nodes.push(node));
+
+			Self::deposit_event(RawEvent::SubdomainCreated(owner, name, expire));
+
+			Ok(())
+		}
+
+		/// Assign a subdomain to `owner`, optionally replacing its expiry. Callable by the owner of
+		/// the parent domain, also while the subdomain is locked by its owner. The lock is lifted.
+		pub fn reassign_subdomain(origin, name: DomainName, owner: T::AccountId, expire: Option<T::BlockNumber>) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let mut domain_detail = <Domains<T>>::get(&node).ok_or_else(|| "domain does not exist")?;
+			let parent_detail = Self::ensure_parent_owner(&who, &domain_detail)?;
+			Self::ensure_not_frozen(&node)?;
+			Self::ensure_not_leased(&node)?;
+
+			if expire.is_some() {
+				domain_detail.expire = Self::subdomain_expire(&parent_detail, expire)?;
+				Self::schedule_release(&name, &domain_detail);
+			}
+
+			let from = domain_detail.owner.clone();
+			<Locks<T>>::remove(&node);
+			Self::set_owner(&node, domain_detail, owner.clone());
+
+			Self::deposit_event(RawEvent::DomainTransferred(name, from, owner));
+
+			Ok(())
+		}
+
+		/// Remove a subdomain and everything under it. Callable by the owner of the parent domain,
+		/// also while the subdomain is locked by its owner.
+		pub fn revoke_subdomain(origin, name: DomainName) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let domain_detail = <Domains<T>>::get(&node).ok_or_else(|| "domain does not exist")?;
+			Self::ensure_parent_owner(&who, &domain_detail)?;
+			Self::ensure_not_frozen(&node)?;
+			Self::ensure_not_leased(&node)?;
+
+			Self::remove_domain(&node);
+
+			Self::deposit_event(RawEvent::SubdomainRevoked(name));
+
+			Ok(())
+		}
+
+		/// Offer the top level domain `name` for sale at `price` of `asset_id`.
+		pub fn list_domain(origin, name: DomainName, asset_id: AssetIdOf<T>, price: BalanceOf<T>) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let domain_detail = Self::ensure_owner(&who, &node)?;
+			Self::ensure_not_leased(&node)?;
+			ensure!(domain_detail.parent.is_none(), "subdomains cannot be sold");
+
+			<Listings<T>>::insert(&node, Listing {
+				seller: who.clone(),
+				asset_id: asset_id.clone(),
+				price: price,
+			});
+
+			Self::deposit_event(RawEvent::DomainListed(name, who, asset_id, price));
+
+			Ok(())
+		}
+
+		/// Take `name` off sale.
+		pub fn cancel_listing(origin, name: DomainName) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let listing = Self::listing(&node).ok_or_else(|| "domain is not for sale")?;
+			ensure!(listing.seller == who, "not seller");
+
+			<Listings<T>>::remove(&node);
+
+			Self::deposit_event(RawEvent::ListingCancelled(name));
+
+			Ok(())
+		}
+
+		/// Buy the listed domain `name`, paying with `paying_asset_id`. The seller receives the listed
+		/// price, converted through CENNZX-Spot from up to `max_paying_amount` if the assets differ.
+		pub fn buy_domain(origin, name: DomainName, paying_asset_id: AssetIdOf<T>, max_paying_amount: BalanceOf<T>) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let listing = Self::listing(&node).ok_or_else(|| "domain is not for sale")?;
+			let domain_detail = Self::active_domain(&node).ok_or_else(|| "domain does not exist")?;
+			ensure!(listing.seller == domain_detail.owner, "listing has lapsed");
+			Self::ensure_unlocked(&node)?;
+			ensure!(who != listing.seller, "cannot buy own domain");
+
+			Self::pay(&who, &paying_asset_id, max_paying_amount, &listing.seller, &listing.asset_id, listing.price)?;
+			Self::set_owner(&node, domain_detail, who.clone());
+
+			Self::deposit_event(RawEvent::DomainSold(name, listing.seller, who, listing.asset_id, listing.price));
+
+			Ok(())
+		}
+
+		/// Offer `amount` of `asset_id` for `name`. The amount is reserved until the offer is accepted
+		/// or withdrawn. An account can have one offer per domain.
+		pub fn make_offer(origin, name: DomainName, asset_id: AssetIdOf<T>, amount: BalanceOf<T>) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let domain_detail = Self::active_domain(&node).ok_or_else(|| "domain does not exist")?;
+			ensure!(domain_detail.parent.is_none(), "subdomains cannot be sold");
+			ensure!(who != domain_detail.owner, "cannot buy own domain");
+			ensure!(!amount.is_zero(), "offer amount is zero");
+			ensure!(!<Offers<T>>::exists((node, who.clone())), "offer already made");
+
+			<generic_asset::Module<T>>::reserve(&asset_id, &who, amount)?;
+
+			<Offers<T>>::insert((node, who.clone()), Offer {
+				asset_id: asset_id.clone(),
+				amount: amount,
+			});
+
+			Self::deposit_event(RawEvent::OfferMade(name, who, asset_id, amount));
+
+			Ok(())
+		}
+
+		/// Withdraw the sender's offer for `name`, returning the reserved amount.
+		pub fn withdraw_offer(origin, name: DomainName) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let offer = <Offers<T>>::take((node, who.clone())).ok_or_else(|| "no offer")?;
+			<generic_asset::Module<T>>::unreserve(&offer.asset_id, &who, offer.amount);
+
+			Self::deposit_event(RawEvent::OfferWithdrawn(name, who));
+
+			Ok(())
+		}
+
+		/// Sell `name` to `buyer` for the amount they offered.
+		pub fn accept_offer(origin, name: DomainName, buyer: T::AccountId) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let domain_detail = Self::ensure_owner(&who, &node)?;
+			Self::ensure_unlocked(&node)?;
+			Self::ensure_not_leased(&node)?;
+			ensure!(domain_detail.parent.is_none(), "subdomains cannot be sold");
+			let offer = Self::offer((node, buyer.clone())).ok_or_else(|| "no offer")?;
+			ensure!(
+				<generic_asset::Module<T>>::reserved_balance(&offer.asset_id, &buyer) >= offer.amount,
+				"offer is not fully reserved"
+			);
+
+			<Offers<T>>::remove((node, buyer.clone()));
+			let shortfall = <generic_asset::Module<T>>::repatriate_reserved(&offer.asset_id, &buyer, &who, offer.amount);
+			ensure!(shortfall.is_zero(), "offer could not be paid in full");
+			Self::set_owner(&node, domain_detail, buyer.clone());
+
+			Self::deposit_event(RawEvent::DomainSold(name, who, buyer, offer.asset_id, offer.amount));
+
+			Ok(())
+		}
+
+		/// Offer to lease `name` to `lessee` for `duration` blocks at `price` of `asset_id`. While the
+		/// lease runs the lessee owns the domain, so they manage its address, records and storefront
+		/// items, but cannot transfer, sell or lock it, nor manage its subdomains. When the lease ends,
+		/// the records and storefront items of the lessee are dropped and the address is restored.
+		/// Replaces any previous lease offer for the domain.
+		pub fn offer_lease(
+			origin,
+			name: DomainName,
+			lessee: T::AccountId,
+			duration: T::BlockNumber,
+			asset_id: AssetIdOf<T>,
+			price: BalanceOf<T>
+		) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			Self::ensure_owner(&who, &node)?;
+			Self::ensure_not_leased(&node)?;
+			ensure!(who != lessee, "cannot lease to self");
+			ensure!(!duration.is_zero(), "lease duration is zero");
+
+			<LeaseOffers<T>>::insert(&node, LeaseOffer {
+				lessor: who.clone(),
+				lessee: lessee.clone(),
+				duration: duration,
+				asset_id: asset_id.clone(),
+				price: price,
+			});
+
+			Self::deposit_event(RawEvent::LeaseOffered(name, who, lessee, duration, asset_id, price));
+
+			Ok(())
+		}
+
+		/// Withdraw the lease offer for `name`. Callable by the lessor or the lessee.
+		pub fn cancel_lease_offer(origin, name: DomainName) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let offer = Self::lease_offer(&node).ok_or_else(|| "no lease offer")?;
+			ensure!(who == offer.lessor || who == offer.lessee, "not party to lease");
+
+			<LeaseOffers<T>>::remove(&node);
+
+			Self::deposit_event(RawEvent::LeaseOfferCancelled(name));
+
+			Ok(())
+		}
+
+		/// Accept the lease of `name` offered to the sender, paying with `paying_asset_id`. The lessor
+		/// receives the price, converted through CENNZX-Spot from up to `max_paying_amount` if the
+		/// assets differ.
+		pub fn accept_lease(origin, name: DomainName, paying_asset_id: AssetIdOf<T>, max_paying_amount: BalanceOf<T>) -> Result {
+			let who = ensure_signed(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			let offer = Self::lease_offer(&node).ok_or_else(|| "no lease offer")?;
+			ensure!(offer.lessee == who, "lease not offered to sender");
+
+			let domain_detail = Self::active_domain(&node).ok_or_else(|| "domain does not exist")?;
+			ensure!(domain_detail.owner == offer.lessor, "lease offer has lapsed");
+			Self::ensure_unlocked(&node)?;
+			Self::ensure_not_leased(&node)?;
+
+			let end = <system::Module<T>>::block_number()
+				.checked_add(&offer.duration)
+				.ok_or_else(|| "lease end overflow")?;
+			ensure!(end <= Self::effective_expire(&domain_detail), "lease runs past domain expiry");
+
+			Self::pay(&who, &paying_asset_id, max_paying_amount, &offer.lessor, &offer.asset_id, offer.price)?;
+			let addr = domain_detail.addr.clone();
+			Self::set_owner(&node, domain_detail, who.clone());
+
+			<Leases<T>>::insert(&node, Lease {
+				lessor: offer.lessor.clone(),
+				lessee: who.clone(),
+				end: end,
+				addr: addr,
+			});
+			<LeaseEnds<T>>::mutate(end, |nodes| nodes.push(node));
+
+			Self::deposit_event(RawEvent::DomainLeased(name, offer.lessor, who, end));
+
+			Ok(())
+		}
+
+		/// Set the number of blocks open auctions, and the commit and reveal phases of sealed auctions
+		/// run for. Auctions already running keep their schedule.
+		pub fn set_auction_periods(
+			origin,
+			auction_period: T::BlockNumber,
+			commit_period: T::BlockNumber,
+			reveal_period: T::BlockNumber
+		) -> Result {
+			ensure_root(origin)?;
+
+			<AuctionPeriod<T>>::put(auction_period);
+			<CommitPeriod<T>>::put(commit_period);
+			<RevealPeriod<T>>::put(reveal_period);
+
+			Ok(())
+		}
+
+		/// Set the soft close of open auctions: a bid within `window` blocks of the end extends the
+		/// auction by `period` blocks, up to `max_extension` blocks past its original end. Auctions
+		/// already running keep their cap.
+		pub fn set_auction_extension(
+			origin,
+			window: T::BlockNumber,
+			period: T::BlockNumber,
+			max_extension: T::BlockNumber
+		) -> Result {
+			ensure_root(origin)?;
+
+			<ExtensionWindow<T>>::put(window);
+			<ExtensionPeriod<T>>::put(period);
+			<MaxExtension<T>>::put(max_extension);
+
+			Ok(())
+		}
+
+		/// Set the minimum bid and the minimum increment over the highest bid of an open auction.
+		pub fn set_minimum_bid(origin, minimum_bid: BalanceOf<T>, minimum_increment: BalanceOf<T>) -> Result {
+			ensure_root(origin)?;
+
+			<MinimumBid<T>>::put(minimum_bid);
+			<MinimumBidIncrement<T>>::put(minimum_increment);
+
+			Ok(())
+		}
+
+		/// Set the number of blocks a domain is registered or renewed for.
+		pub fn set_registration_period(origin, period: T::BlockNumber) -> Result {
+			ensure_root(origin)?;
+
+			<RegistrationPeriod<T>>::put(period);
+
+			Ok(())
+		}
+
+		/// Freeze `name` until `unfreeze_domain`, with the same effect as an owner lock.
+		pub fn freeze_domain(origin, name: DomainName) -> Result {
+			ensure_root(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			ensure!(<Domains<T>>::exists(&node), "domain does not exist");
+			<Frozen<T>>::insert(&node, true);
+
+			Self::deposit_event(RawEvent::DomainFrozen(name, true));
+
+			Ok(())
+		}
+
+		/// Lift the freeze of `name`.
+		pub fn unfreeze_domain(origin, name: DomainName) -> Result {
+			ensure_root(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			<Frozen<T>>::remove(&node);
+
+			Self::deposit_event(RawEvent::DomainFrozen(name, false));
+
+			Ok(())
+		}
+
+		/// Reserve `name` so that it can only be assigned by root, to `owner` if given. A running auction
+		/// for it is closed without registering it, refunding the highest bid.
+		pub fn reserve_name(origin, name: DomainName, owner: Option<T::AccountId>) -> Result {
+			ensure_root(origin)?;
+			let name = normalize_name(&name)?;
+
+			Self::set_restriction(name, Some(NameRestriction::Reserved(owner)));
+
+			Ok(())
+		}
+
+		/// Block `name` from ever being registered. Does not affect a current registration. A running
+		/// auction for it is closed without registering it, refunding the highest bid.
+		pub fn block_name(origin, name: DomainName) -> Result {
+			ensure_root(origin)?;
+			let name = normalize_name(&name)?;
+
+			Self::set_restriction(name, Some(NameRestriction::Blocked));
+
+			Ok(())
+		}
+
+		/// Lift the reservation or block of `name`.
+		pub fn clear_restriction(origin, name: DomainName) -> Result {
+			ensure_root(origin)?;
+			let name = normalize_name(&name)?;
+
+			Self::set_restriction(name, None);
+
+			Ok(())
+		}
+
+		/// Register the reserved name `name` to `owner` for one registration period. Names reserved while
+		/// in auction can be assigned once the auction has ended.
+		pub fn assign_reserved(origin, name: DomainName, owner: T::AccountId, addr: Option<DomainAddr>) -> Result {
+			ensure_root(origin)?;
+			let (name, node) = Self::hash_name(&name)?;
+
+			match Self::restriction(&node) {
+				Some(NameRestriction::Reserved(None)) => (),
+				Some(NameRestriction::Reserved(Some(ref reserved_for))) if *reserved_for == owner => (),
+				Some(NameRestriction::Reserved(Some(_))) => return Err("domain is reserved for another account"),
+				_ => return Err("domain is not reserved"),
+			}
+			ensure!(!<Domains<T>>::exists(&node), "domain already registered");
+			ensure!(!<Bids<T>>::exists(&node), "domain is in auction");
+
+			let expire = <system::Module<T>>::block_number() + Self::registration_period();
+			Self::register(&name, owner.clone(), expire, addr);
+
+			Self::deposit_event(RawEvent::DomainRegistered(owner, name, expire));
+
+			Ok(())
+		}
+
+		/// Set the renewal price schedule, see `PriceSchedule`.
+		pub fn set_price_schedule(origin, prices: Vec<BalanceOf<T>>) -> Result {
+			ensure_root(origin)?;
+
+			<PriceSchedule<T>>::put(prices);
+
+			Ok(())
+		}
+
+		/// Set the deposit reserved for each record. Existing records keep the deposit they were set with.
+		pub fn set_record_deposit(origin, deposit: BalanceOf<T>) -> Result {
+			ensure_root(origin)?;
+
+			<RecordDeposit<T>>::put(deposit);
+
+			Ok(())
+		}
+
+		/// Set the number of blocks an expired domain is held for its owner. Names already past expiry
+		/// are released no earlier than the grace period they were scheduled with.
+		pub fn set_grace_period(origin, period: T::BlockNumber) -> Result {
+			ensure_root(origin)?;
+
+			<GracePeriod<T>>::put(period);
+
+			Ok(())
+		}
+
+		/// Set the share of auction proceeds and renewal fees that is burned.
+		pub fn set_burn_share(origin, share: Permill) -> Result {
+			ensure_root(origin)?;
+
+			<BurnShare<T>>::put(share);
+
+			Ok(())
+		}
+
+		/// Move the entries of `names` from the baseline layout, keyed by plain name, to name hash
+		/// keys. Names without baseline entries are skipped, see `migrate_name`. Until then, bids on
+		/// names with baseline entries are rejected, as given or normalised.
+		pub fn migrate_names(origin, names: Vec<DomainName>) -> Result {
+			ensure_root(origin)?;
+
+			for name in names {
+				Self::migrate_name(&name);
+			}
+
+			Ok(())
+		}
+
+		/// Migrate the names listed in the baseline `Owners` entries of `accounts` like
+		/// `migrate_names`, removing those entries.
+		pub fn migrate_owners(origin, accounts: Vec<T::AccountId>) -> Result {
+			ensure_root(origin)?;
+
+			for account in accounts {
+				if let Some(names) = take_legacy::<_, Vec<DomainName>>(b"DomainService Owners", &account) {
+					for name in names {
+						Self::migrate_name(&name);
+					}
+				}
+			}
+
+			Ok(())
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The asset bids are escrowed and paid in.
+	pub fn settlement_asset_id() -> AssetIdOf<T> {
+		<generic_asset::Module<T>>::spending_asset_id()
+	}
+
+	/// Place a bid in the open auction for `name`, starting it if there is none yet. With `payment`
+	/// given as `(paying_asset_id, max_paying_amount)` the bid is bought from that asset first.
+	fn place_bid(
+		who: T::AccountId,
+		name: DomainName,
+		amount: BalanceOf<T>,
+		payment: Option<(AssetIdOf<T>, BalanceOf<T>)>,
+	) -> Result {
+		ensure!(!Self::awaits_migration(&name), "domain awaits migration");
+		let (name, node) = Self::hash_name(&name)?;
+
+		ensure!(!name.contains(&LABEL_SEPARATOR), "subdomains cannot be auctioned");
+		ensure!(!Self::awaits_migration(&name), "domain awaits migration");
+		ensure!(!<Domains<T>>::exists(&node), "domain already registered");
+		ensure!(Self::restriction(&node).is_none(), "domain is reserved or blocked");
+
+		let previous = <Bids<T>>::get(&node);
+		match previous {
+			Some(ref bid_info) => {
+				ensure!(bid_info.sealed.is_none(), "domain is in a sealed auction");
+				let minimum = bid_info.bid.amount.saturating_add(Self::minimum_bid_increment());
+				if amount <= bid_info.bid.amount || amount < minimum {
+					return Err("bid amount too small")
+				}
+			}
+			None => ensure!(amount >= Self::minimum_bid(), "bid amount too small"),
+		}
+
+		let asset_id = Self::settlement_asset_id();
+
+		if let Some((paying_asset_id, max_paying_amount)) = payment {
+			if paying_asset_id == asset_id {
+				ensure!(amount <= max_paying_amount, "bid exceeds max paying amount");
+			} else {
+				<cennzx_spot::Module<T>>::make_asset_swap_output(
+					&who,
+					&who,
+					&paying_asset_id,
+					&asset_id,
+					amount,
+					max_paying_amount,
+					<cennzx_spot::Module<T>>::fee_rate()
+				)?;
+			}
+		}
+		<generic_asset::Module<T>>::reserve(&asset_id, &who, amount)?;
+
+		let now = <system::Module<T>>::block_number();
+		let (end, latest_end) = match previous {
+			Some(bid_info) => {
+				// refund the bid being replaced
+				<generic_asset::Module<T>>::unreserve(&asset_id, &bid_info.bid.bidder, bid_info.bid.amount);
+
+				Self::deposit_event(RawEvent::Outbid(bid_info.bid.bidder, name.clone(), bid_info.bid.amount));
+
+				let mut end = bid_info.end;
+				if now + Self::extension_window() > end {
+					end = (end + Self::extension_period()).min(bid_info.latest_end);
+					if end != bid_info.end {
+						<AuctionEnds<T>>::mutate(bid_info.end, |names| names.retain(|n| *n != name));
+						<AuctionEnds<T>>::mutate(end, |names| names.push(name.clone()));
+
+						Self::deposit_event(RawEvent::AuctionExtended(name.clone(), end));
+					}
+				}
+
+				(end, bid_info.latest_end)
+			}
+			None => {
+				let end = now + Self::auction_period();
+				<AuctionEnds<T>>::mutate(end, |names| names.push(name.clone()));
+				<Names<T>>::insert(&node, &name);
+				<RunningAuctions<T>>::mutate(|nodes| nodes.push(node));
+
+				(end, end + Self::max_extension())
+			}
+		};
+
+		<Bids<T>>::insert(&node, BidInfo {
+			bid: Bid {
+				bidder: who.clone(),
+				amount: amount,
+			},
+			end: end,
+			latest_end: latest_end,
+			sealed: None,
+		});
+
+		Self::deposit_event(RawEvent::BidPlaced(who, name, amount));
+
+		Ok(())
+	}
+
+	/// Pay `amount` of `asset_id` from `from` to `to`. A different `paying_asset_id` is converted
+	/// through CENNZX-Spot, spending at most `max_paying_amount` of it.
+	fn pay(
+		from: &T::AccountId,
+		paying_asset_id: &AssetIdOf<T>,
+		max_paying_amount: BalanceOf<T>,
+		to: &T::AccountId,
+		asset_id: &AssetIdOf<T>,
+		amount: BalanceOf<T>,
+	) -> Result {
+		if paying_asset_id == asset_id {
+			ensure!(amount <= max_paying_amount, "price exceeds max paying amount");
+			<generic_asset::Module<T>>::make_transfer_with_event(asset_id, from, to, amount)
+		} else {
+			<cennzx_spot::Module<T>>::make_asset_swap_output(
+				from,
+				to,
+				paying_asset_id,
+				asset_id,
+				amount,
+				max_paying_amount,
+				<cennzx_spot::Module<T>>::fee_rate()
+			)?;
+			Ok(())
+		}
+	}
+
+	/// Close the auction for `name` if it ends at `now`, charging the winner and registering the
+	/// domain to them. Sealed auction winners pay the second highest revealed amount, but no less
+	/// than the minimum bid. Unrevealed deposits are forfeited. If the name was reserved, blocked or
+	/// registered while the auction ran, the winner is refunded instead.
+	fn settle_auction(name: DomainName, now: T::BlockNumber) {
+		let node = Self::namehash(&name);
+		let bid_info = match Self::bids(&node) {
+			Some(bid_info) if bid_info.end == now => bid_info,
+			_ => return,
+		};
+		<Bids<T>>::remove(&node);
+		<RunningAuctions<T>>::mutate(|nodes| nodes.retain(|n| *n != node));
+
+		let bid = bid_info.bid;
+		let asset_id = Self::settlement_asset_id();
+		let registered = <Domains<T>>::exists(&node);
+		let available = !registered && Self::restriction(&node).is_none();
+
+		let price = if let Some(sealed) = bid_info.sealed {
+			for committer in <Committers<T>>::take(&node) {
+				if let Some((_, deposit)) = <Commitments<T>>::take((node, committer.clone())) {
+					Self::collect_payment(&committer, deposit);
+					Self::deposit_event(RawEvent::DepositForfeited(committer, name.clone(), deposit));
+				}
+			}
+
+			// nobody revealed
+			if bid.amount.is_zero() {
+				if !registered {
+					<Names<T>>::remove(&node);
+				}
+				return;
+			}
+
+			// the minimum bid may have been raised since the reveal
+			sealed.second_price.max(Self::minimum_bid()).min(bid.amount)
+		} else {
+			bid.amount
+		};
+
+		if !available {
+			if !registered {
+				<Names<T>>::remove(&node);
+			}
+			<generic_asset::Module<T>>::unreserve(&asset_id, &bid.bidder, bid.amount);
+
+			Self::deposit_event(RawEvent::AuctionCancelled(bid.bidder, name, bid.amount));
+			return;
+		}
+
+		<generic_asset::Module<T>>::unreserve(&asset_id, &bid.bidder, bid.amount - price);
+		Self::collect_payment(&bid.bidder, price);
+
+		Self::deposit_event(RawEvent::AuctionWon(bid.bidder.clone(), name.clone(), price));
+
+		let expire = bid_info.end + Self::registration_period();
+		Self::register(&name, bid.bidder.clone(), expire, None);
+
+		Self::deposit_event(RawEvent::DomainRegistered(bid.bidder, name, expire));
+	}
+
+	/// Register the top level domain `name` to `owner`.
+	fn register(name: &DomainName, owner: T::AccountId, expire: T::BlockNumber, addr: Option<DomainAddr>) {
+		let node = Self::namehash(name);
+		let domain_detail = DomainDetail {
+			owner: owner,
+			expire: expire,
+			addr: addr,
+			parent: None,
+		};
+
+		Self::add_owned_domain(&domain_detail.owner, &node);
+		Self::schedule_release(name, &domain_detail);
+		<Domains<T>>::insert(&node, domain_detail);
+		<Names<T>>::insert(&node, name);
+	}
+
+	fn set_restriction(name: DomainName, restriction: Option<NameRestriction<T::AccountId>>) {
+		let node = Self::namehash(&name);
+		match restriction {
+			Some(ref restriction) => <Restrictions<T>>::insert(&node, restriction),
+			None => <Restrictions<T>>::remove(&node),
+		}
+
+		Self::deposit_event(RawEvent::RestrictionChanged(name, restriction));
+	}
+
+	/// Up to `count` names owned by `who`, starting from position `start` of their owner index.
+	pub fn names_of(who: &T::AccountId, start: u64, count: u64) -> Vec<DomainName> {
+		let end = Self::owned_domains_count(who).min(start.saturating_add(count));
+		(start..end)
+			.filter_map(|index| Self::owned_domain((who.clone(), index)))
+			.filter_map(Self::name_of)
+			.collect()
+	}
+
+	/// Hand the domain `node` over to `to`, keeping the owner index in step with the registry.
+	fn set_owner(node: &T::Hash, mut domain_detail: DomainDetail<T::AccountId, T::BlockNumber>, to: T::AccountId) {
+		Self::remove_owned_domain(&domain_detail.owner, node);
+		Self::add_owned_domain(&to, node);
+		<Listings<T>>::remove(node);
+		<Approvals<T>>::remove(node);
+		<PendingTransfers<T>>::remove(node);
+		<LeaseOffers<T>>::remove(node);
+
+		domain_detail.owner = to;
+		<Domains<T>>::insert(node, domain_detail);
+	}
+
+	fn add_owned_domain(owner: &T::AccountId, node: &T::Hash) {
+		let count = Self::owned_domains_count(owner);
+
+		<OwnedDomains<T>>::insert((owner.clone(), count), node);
+		<OwnedDomainsIndex<T>>::insert(node, count);
+		<OwnedDomainsCount<T>>::insert(owner, count + 1);
+	}
+
+	/// Remove `node` from the domains of `owner`, moving their last domain into the freed position.
+	fn remove_owned_domain(owner: &T::AccountId, node: &T::Hash) {
+		let index = <OwnedDomainsIndex<T>>::get(node);
+		if Self::owned_domain((owner.clone(), index)).as_ref() != Some(node) {
+			return;
+		}
+
+		let last = Self::owned_domains_count(owner) - 1;
+		if index != last {
+			if let Some(last_node) = <OwnedDomains<T>>::get((owner.clone(), last)) {
+				<OwnedDomains<T>>::insert((owner.clone(), index), &last_node);
+				<OwnedDomainsIndex<T>>::insert(&last_node, index);
+			}
+		}
+
+		<OwnedDomains<T>>::remove((owner.clone(), last));
+		<OwnedDomainsIndex<T>>::remove(node);
+		<OwnedDomainsCount<T>>::insert(owner, last);
+	}
+
+	/// The price of renewing `name` for `periods` registration periods.
+	pub fn renewal_price(name: &DomainName, periods: u32) -> result::Result<BalanceOf<T>, &'static str> {
+		let schedule = Self::price_schedule();
+		let price = schedule.get(name.len().saturating_sub(1))
+			.or_else(|| schedule.last())
+			.cloned()
+			.unwrap_or_else(Zero::zero);
+
+		price.checked_mul(&BalanceOf::<T>::sa(periods as u64)).ok_or_else(|| "renewal price overflow")
+	}
+
+	/// Normalise `name`, returning it together with its name hash.
+	pub fn hash_name(name: &[u8]) -> result::Result<(DomainName, T::Hash), &'static str> {
+		let name = normalize_name(name)?;
+		let node = Self::namehash(&name);
+		Ok((name, node))
+	}
+
+	/// The name hash of the normalised `name`, computed label by label as in ENS: the empty name
+	/// hashes to zero and `label.parent` to the hash of the parent's name hash followed by the hash
+	/// of `label`.
+	pub fn namehash(name: &[u8]) -> T::Hash {
+		name.rsplit(|c| *c == LABEL_SEPARATOR)
+			.fold(Default::default(), |node, label| Self::subnode(&node, label))
+	}
+
+	/// The name hash of subdomain `label` under the domain with name hash `parent`.
+	pub fn subnode(parent: &T::Hash, label: &[u8]) -> T::Hash {
+		let mut data = parent.as_ref().to_vec();
+		data.extend_from_slice(T::Hashing::hash(label).as_ref());
+		T::Hashing::hash(&data)
+	}
+
+	/// The account an active domain resolves to through its address.
+	pub fn resolve(node: &T::Hash) -> Option<T::AccountId> {
+		Self::active_domain(node)
+			.and_then(|detail| detail.addr)
+			.and_then(|addr| Decode::decode(&mut &addr[..]))
+	}
+
+	/// Whether the normalised `name` can be bid on: a top level name that is neither registered nor
+	/// restricted, nor waiting to be migrated.
+	pub fn is_available(name: &DomainName) -> bool {
+		let node = Self::namehash(name);
+		!name.contains(&LABEL_SEPARATOR)
+			&& !<Domains<T>>::exists(&node)
+			&& Self::restriction(&node).is_none()
+			&& !Self::awaits_migration(name)
+	}
+
+	/// Whether `name` still has baseline `Domains` or `Bids` entries keyed by it. Such names cannot be
+	/// bid on until they are migrated, see `migrate_names`.
+	fn awaits_migration(name: &DomainName) -> bool {
+		legacy_exists(b"DomainService Domains", name) || legacy_exists(b"DomainService Bids", name)
+	}
+
+	/// All running auctions.
+	pub fn auctions() -> Vec<(DomainName, BidInfo<T::AccountId, BalanceOf<T>, T::BlockNumber>)> {
+		Self::running_auctions()
+			.into_iter()
+			.filter_map(|node| Some((Self::name_of(&node)?, Self::bids(&node)?)))
+			.collect()
+	}
+
+	/// The primary name of `who`, as long as it still resolves to them.
+	pub fn reverse(who: &T::AccountId) -> Option<DomainName> {
+		Self::primary_name(who).filter(|name| Self::resolve(&Self::namehash(name)).as_ref() == Some(who))
+	}
+
+	/// Check that `who` owns the active domain `node`, returning its registration.
+	fn ensure_owner(
+		who: &T::AccountId,
+		node: &T::Hash,
+	) -> result::Result<DomainDetail<T::AccountId, T::BlockNumber>, &'static str> {
+		let domain_detail = <Domains<T>>::get(node).ok_or_else(|| "domain does not exist")?;
+		if *who != domain_detail.owner {
+			return Err("not owner")
+		}
+		Self::ensure_not_expired(&domain_detail)?;
+		Ok(domain_detail)
+	}
+
+	/// Whether `who` controls the domain `node` owned by `owner`: they are the owner, the account
+	/// approved for the domain, or an operator of the owner.
+	fn is_controller(who: &T::AccountId, node: &T::Hash, owner: &T::AccountId) -> bool {
+		who == owner
+			|| Self::approved(node).as_ref() == Some(who)
+			|| Self::is_operator((owner.clone(), who.clone()))
+	}
+
+	/// Check that `who` controls the active domain `node`, returning its registration.
+	fn ensure_controller(
+		who: &T::AccountId,
+		node: &T::Hash,
+	) -> result::Result<DomainDetail<T::AccountId, T::BlockNumber>, &'static str> {
+		let domain_detail = <Domains<T>>::get(node).ok_or_else(|| "domain does not exist")?;
+		if !Self::is_controller(who, node, &domain_detail.owner) {
+			return Err("not owner or approved")
+		}
+		Self::ensure_not_expired(&domain_detail)?;
+		Ok(domain_detail)
+	}
+
+	/// Check that the domain `node` is neither locked by its owner nor frozen by root.
+	pub fn ensure_unlocked(node: &T::Hash) -> Result {
+		Self::ensure_not_frozen(node)?;
+		if let Some(locked_until) = Self::locked_until(node) {
+			ensure!(<system::Module<T>>::block_number() > locked_until, "domain is locked");
+		}
+		Ok(())
+	}
+
+	/// Check that the domain `node` is not frozen by root.
+	fn ensure_not_frozen(node: &T::Hash) -> Result {
+		ensure!(!Self::is_frozen(node), "domain is frozen");
+		Ok(())
+	}
+
+	/// Check that the domain `node` is not leased out. Leased domains cannot change hands other than
+	/// by returning to the lessor.
+	fn ensure_not_leased(node: &T::Hash) -> Result {
+		ensure!(!<Leases<T>>::exists(node), "domain is leased");
+		Ok(())
+	}
+
+	/// A record of `value` with the record deposit reserved from `who`.
+	fn new_record<V>(who: &T::AccountId, value: V) -> result::Result<RecordOf<T, V>, &'static str> {
+		let deposit = Self::record_deposit();
+		<generic_asset::Module<T>>::reserve(&Self::settlement_asset_id(), who, deposit)?;
+
+		Ok(Record {
+			value: value,
+			depositor: who.clone(),
+			deposit: deposit,
+		})
+	}
+
+	/// Return the deposit of a removed record.
+	fn release_record<V>(record: RecordOf<T, V>) {
+		<generic_asset::Module<T>>::unreserve(&Self::settlement_asset_id(), &record.depositor, record.deposit);
+	}
+
+	/// Remove all records of `node`, returning their deposits.
+	fn clear_records(node: &T::Hash) {
+		Self::clear_records_where(node, |_| true);
+	}
+
+	/// Remove the records of `node` whose depositor matches `clear`, returning their deposits.
+	fn clear_records_where<F: Fn(&T::AccountId) -> bool>(node: &T::Hash, clear: F) {
+		let mut kept_keys = Vec::new();
+		for key in <TextRecordKeys<T>>::take(node) {
+			match <TextRecords<T>>::get((*node, key.clone())) {
+				Some(ref record) if !clear(&record.depositor) => kept_keys.push(key),
+				Some(record) => {
+					<TextRecords<T>>::remove((*node, key));
+					Self::release_record(record);
+				}
+				None => (),
+			}
+		}
+		if !kept_keys.is_empty() {
+			<TextRecordKeys<T>>::insert(node, kept_keys);
+		}
+
+		if let Some(record) = <ContentHashes<T>>::get(node) {
+			if clear(&record.depositor) {
+				<ContentHashes<T>>::remove(node);
+				Self::release_record(record);
+			}
+		}
+
+		let mut kept_assets = Vec::new();
+		for asset_id in <AssetAddressAssets<T>>::take(node) {
+			match <AssetAddresses<T>>::get((*node, asset_id.clone())) {
+				Some(ref record) if !clear(&record.depositor) => kept_assets.push(asset_id),
+				Some(record) => {
+					<AssetAddresses<T>>::remove((*node, asset_id));
+					Self::release_record(record);
+				}
+				None => (),
+			}
+		}
+		if !kept_assets.is_empty() {
+			<AssetAddressAssets<T>>::insert(node, kept_assets);
+		}
+	}
+
+	/// The registration of the domain `node`, if it has not expired.
+	pub fn active_domain(node: &T::Hash) -> Option<DomainDetail<T::AccountId, T::BlockNumber>> {
+		Self::domains(node).filter(|detail| Self::ensure_not_expired(detail).is_ok())
+	}
+
+	fn ensure_not_expired(detail: &DomainDetail<T::AccountId, T::BlockNumber>) -> Result {
+		ensure!(<system::Module<T>>::block_number() <= Self::effective_expire(detail), "domain expired");
+		Ok(())
+	}
+
+	/// The expiry of a domain, taking the expiry of the domains above it into account.
+	fn effective_expire(detail: &DomainDetail<T::AccountId, T::BlockNumber>) -> T::BlockNumber {
+		match detail.parent {
+			Some(ref parent) => Self::domains(Self::namehash(parent))
+				.map(|parent_detail| Self::effective_expire(&parent_detail).min(detail.expire))
+				.unwrap_or_else(Zero::zero),
+			None => detail.expire,
+		}
+	}
+
+	/// Check that `who` owns the active parent of the subdomain `detail` and that the parent is not
+	/// leased out, returning the parent.
+	fn ensure_parent_owner(
+		who: &T::AccountId,
+		detail: &DomainDetail<T::AccountId, T::BlockNumber>,
+	) -> result::Result<DomainDetail<T::AccountId, T::BlockNumber>, &'static str> {
+		let parent = detail.parent.as_ref().ok_or_else(|| "not a subdomain")?;
+		let parent_node = Self::namehash(parent);
+		let parent_detail = Self::active_domain(&parent_node).ok_or_else(|| "parent domain does not exist")?;
+		if *who != parent_detail.owner {
+			return Err("not owner of parent domain")
+		}
+		Self::ensure_not_leased(&parent_node)?;
+		Ok(parent_detail)
+	}
+
+	/// The expiry for a new subdomain of `parent_detail`, which may not outlive it.
+	fn subdomain_expire(
+		parent_detail: &DomainDetail<T::AccountId, T::BlockNumber>,
+		expire: Option<T::BlockNumber>,
+	) -> result::Result<T::BlockNumber, &'static str> {
+		match expire {
+			Some(expire) => {
+				ensure!(expire <= Self::effective_expire(parent_detail), "subdomain cannot outlive its parent");
+				ensure!(expire >= <system::Module<T>>::block_number(), "expiry is in the past");
+				Ok(expire)
+			}
+			None => Ok(T::BlockNumber::max_value()),
+		}
+	}
+
+	/// The block at the end of which an expired domain is released. Subdomains have no grace period.
+	fn release_block(detail: &DomainDetail<T::AccountId, T::BlockNumber>) -> T::BlockNumber {
+		if detail.parent.is_some() {
+			detail.expire
+		} else {
+			detail.expire + Self::grace_period()
+		}
+	}
+
+	/// Check `name` for release once it is due. Subdomains without an expiry of their own are
+	/// released together with their parent.
+	fn schedule_release(name: &DomainName, detail: &DomainDetail<T::AccountId, T::BlockNumber>) {
+		if detail.expire != T::BlockNumber::max_value() {
+			<Expiries<T>>::mutate(Self::release_block(detail), |names| names.push(name.clone()));
+		}
+	}
+
+	/// Remove the domain `node` and all of its subdomains from the registry, together with their
+	/// records.
+	fn remove_domain(node: &T::Hash) {
+		for subdomain in <Subdomains<T>>::take(node) {
+			Self::remove_domain(&subdomain);
+		}
+
+		Self::clear_records(node);
+		T::OnDomainRemoved::on_domain_removed(node);
+		<Names<T>>::remove(node);
+		<Listings<T>>::remove(node);
+		<Approvals<T>>::remove(node);
+		<PendingTransfers<T>>::remove(node);
+		<Locks<T>>::remove(node);
+		<Frozen<T>>::remove(node);
+		<LeaseOffers<T>>::remove(node);
+		<Leases<T>>::remove(node);
+
+		if let Some(domain_detail) = <Domains<T>>::take(node) {
+			Self::remove_owned_domain(&domain_detail.owner, node);
+			if let Some(parent) = domain_detail.parent {
+				<Subdomains<T>>::mutate(Self::namehash(&parent), |nodes| nodes.retain(|n| n != node));
+			}
+		}
+	}
+
+	/// Remove `name` and its subdomains from the registry if it is past its grace period, otherwise
+	/// check it again when it will be. A renewed domain is thereby rescheduled by the check for its
+	/// previous expiry.
+	fn release_expired(name: DomainName, now: T::BlockNumber) {
+		let node = Self::namehash(&name);
+		if let Some(domain_detail) = Self::domains(&node) {
+			let release_block = Self::release_block(&domain_detail);
+			if release_block > now {
+				<Expiries<T>>::mutate(release_block, |names| names.push(name));
+				return;
+			}
+
+			Self::remove_domain(&node);
+
+			Self::deposit_event(RawEvent::DomainExpired(name));
+		}
+	}
+
+	/// Return the domain `node` to its lessor if its lease ends at `now`. The records not set by the
+	/// lessor are cleared and the address from before the lease is restored, so that nothing set by
+	/// the lessee carries over. Leases of removed domains have already been dropped.
+	fn end_lease(node: &T::Hash, now: T::BlockNumber) {
+		let lease = match Self::lease(node) {
+			Some(lease) if lease.end == now => lease,
+			_ => return,
+		};
+		<Leases<T>>::remove(node);
+
+		if let Some(mut domain_detail) = Self::domains(node) {
+			Self::clear_records_where(node, |depositor| *depositor != lease.lessor);
+			T::OnLeaseEnded::on_lease_ended(node, &lease.lessee);
+			domain_detail.addr = lease.addr;
+			Self::set_owner(node, domain_detail, lease.lessor.clone());
+
+			let name = Self::name_of(node).unwrap_or_default();
+			Self::deposit_event(RawEvent::LeaseEnded(name, lease.lessor, lease.lessee));
+		}
+	}
+
+	/// The account holding the revenue pool.
+	pub fn pool_account() -> T::AccountId {
+		T::PoolAddressGenerator::pool_address()
+	}
+
+	/// Take `amount` out of the reserved balance of `who` into the revenue pool, burning the burn
+	/// share of it.
+	fn collect_payment(who: &T::AccountId, amount: BalanceOf<T>) {
+		let asset_id = Self::settlement_asset_id();
+		let burned = Self::burn_share() * amount;
+		let pooled = amount - burned;
+
+		let _ = <generic_asset::Module<T>>::slash_reserved(&asset_id, who, burned);
+		let _ = <generic_asset::Module<T>>::repatriate_reserved(&asset_id, who, &Self::pool_account(), pooled);
+
+		Self::deposit_event(RawEvent::RevenueCollected(pooled, burned));
+	}
+
+	/// Record that `amount` of the revenue pool was paid out to stakers. The payout itself is done by
+	/// the runtime's staking reward handler.
+	pub fn note_distributed(amount: BalanceOf<T>) {
+		Self::deposit_event(RawEvent::RevenueDistributed(amount));
+	}
+
+	/// Move the baseline `Domains` and `Bids` entries of `raw_name` to the name hash of its normalised
+	/// form. Names that are not valid top level names, or whose name hash is already in use, are left
+	/// in place.
+	fn migrate_name(raw_name: &DomainName) {
+		let name = match normalize_name(raw_name) {
+			Ok(ref name) if !name.contains(&LABEL_SEPARATOR) => name.clone(),
+			_ => return,
+		};
+		let node = Self::namehash(&name);
+		if <Names<T>>::exists(&node) {
+			return;
+		}
+		let now = <system::Module<T>>::block_number();
+
+		if let Some(legacy) = take_legacy::<_, LegacyDomainDetail<T::AccountId, T::BlockNumber>>(b"DomainService Domains", raw_name) {
+			let domain_detail = DomainDetail {
+				owner: legacy.owner,
+				expire: legacy.expire,
+				addr: legacy.addr,
+				parent: None,
+			};
+
+			// baseline `Owners` lists missed transfer recipients, so index by the registered owner
+			Self::add_owned_domain(&domain_detail.owner, &node);
+			// domains already past their grace period are released at the end of this block
+			<Expiries<T>>::mutate(Self::release_block(&domain_detail).max(now), |names| names.push(name.clone()));
+			<Domains<T>>::insert(&node, domain_detail);
+			<Names<T>>::insert(&node, &name);
+		}
+
+		if let Some(legacy) = take_legacy::<_, LegacyBidInfo<T::AccountId, T::BlockNumber>>(b"DomainService Bids", raw_name) {
+			// baseline bids were not escrowed, so a bid is only kept if its amount can be reserved
+			// now. Bids for registered names were never settled and are dropped.
+			if <Names<T>>::exists(&node) || legacy.bid.amount > u128::from(u64::max_value()) {
+				return;
+			}
+			let amount = BalanceOf::<T>::sa(legacy.bid.amount as u64);
+			if <generic_asset::Module<T>>::reserve(&Self::settlement_asset_id(), &legacy.bid.bidder, amount).is_err() {
+				return;
+			}
+
+			// auctions already past their end are settled at the end of this block
+			let end = legacy.end.max(now);
+			<Bids<T>>::insert(&node, BidInfo {
+				bid: Bid {
+					bidder: legacy.bid.bidder,
+					amount: amount,
+				},
+				end: end,
+				latest_end: end,
+				sealed: None,
+			});
+			<AuctionEnds<T>>::mutate(end, |names| names.push(name.clone()));
+			<Names<T>>::insert(&node, &name);
+			<RunningAuctions<T>>::mutate(|nodes| nodes.push(node));
+		}
+	}
+}
+
+/// Baseline layout of `DomainDetail`, keyed by plain name. See `migrate_names`.
+#[derive(Decode)]
+struct LegacyDomainDetail<AccountId, BlockNumber> {
+	owner: AccountId,
+	expire: BlockNumber,
+	addr: Option<DomainAddr>,
+}
+
+/// Baseline layout of `BidInfo`, keyed by plain name. See `migrate_names`.
+#[derive(Decode)]
+struct LegacyBidInfo<AccountId, BlockNumber> {
+	bid: LegacyBid<AccountId>,
+	end: BlockNumber,
+}
+
+#[derive(Decode)]
+struct LegacyBid<AccountId> {
+	bidder: AccountId,
+	#[allow(dead_code)]
+	name: DomainName,
+	amount: u128,
+}
+
+/// The raw storage key of `key` in the `map` storage item with the given prefix, e.g.
+/// `DomainService Domains`, regardless of its current key type. Used to migrate storage layouts.
+fn legacy_key<K: Encode>(prefix: &[u8], key: &K) -> [u8; 32] {
+	let mut raw_key = prefix.to_vec();
+	key.encode_to(&mut raw_key);
+	blake2_256(&raw_key)
+}
+
+/// The value of `key` in the `map` storage item with the given prefix, if it decodes as `V`.
+pub fn get_legacy<K: Encode, V: Decode>(prefix: &[u8], key: &K) -> Option<V> {
+	unhashed::get(&legacy_key(prefix, key))
+}
+
+/// Whether the `map` storage item with the given prefix has a value for `key`, decodable or not.
+fn legacy_exists<K: Encode>(prefix: &[u8], key: &K) -> bool {
+	unhashed::exists(&legacy_key(prefix, key))
+}
+
+/// Remove `key` from the `map` storage item with the given prefix.
+fn kill_legacy<K: Encode>(prefix: &[u8], key: &K) {
+	unhashed::kill(&legacy_key(prefix, key));
+}
+
+/// Take the value of `key` out of the `map` storage item with the given prefix if it decodes as `V`.
+/// Values that do not decode are left in place.
+pub fn take_legacy<K: Encode, V: Decode>(prefix: &[u8], key: &K) -> Option<V> {
+	let value = get_legacy(prefix, key);
+	if value.is_some() {
+		kill_legacy(prefix, key);
+	}
+	value
+}
+
+/// Normalise `name` to lowercase and check it is a valid domain name: one or more labels of `a-z`,
+/// `0-9` and `-` separated by `LABEL_SEPARATOR`, where no label starts or ends with `-`.
+pub fn normalize_name(name: &[u8]) -> result::Result<DomainName, &'static str> {
+	ensure!(!name.is_empty(), "domain name is empty");
+	ensure!(name.len() <= MAX_NAME_LENGTH, "domain name too long");
+
+	let name = name.to_ascii_lowercase();
+	for label in name.split(|c| *c == LABEL_SEPARATOR) {
+		ensure!(!label.is_empty(), "domain name has an empty label");
+		ensure!(label.len() <= MAX_LABEL_LENGTH, "domain name label too long");
+		ensure!(
+			label.iter().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == b'-'),
+			"domain name contains an invalid character"
+		);
+		ensure!(label[0] != b'-' && label[label.len() - 1] != b'-', "domain name label starts or ends with '-'");
+	}
+
+	Ok(name)
+}
+
+/// The full name of subdomain `label` under `parent`.
+fn subdomain_name(label: &DomainName, parent: &DomainName) -> DomainName {
+	let mut name = label.clone();
+	name.push(LABEL_SEPARATOR);
+	name.extend_from_slice(parent);
+	name
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as system::Trait>::AccountId,
+		<T as system::Trait>::BlockNumber,
+		Balance = BalanceOf<T>,
+		AssetId = AssetIdOf<T>,
+	{
+		/// Bid placed in an open auction. (bidder, name, amount)
+		BidPlaced(AccountId, DomainName, Balance),
+		/// Highest bid replaced by a higher one and refunded. (bidder, name, amount)
+		Outbid(AccountId, DomainName, Balance),
+		/// Open auction extended by a bid near its end. (name, new_end)
+		AuctionExtended(DomainName, BlockNumber),
+		/// Sealed bid committed. (bidder, name)
+		BidCommitted(AccountId, DomainName),
+		/// Sealed bid revealed. (bidder, name, amount)
+		BidRevealed(AccountId, DomainName, Balance),
+		/// Deposit of an unrevealed sealed bid forfeited. (bidder, name, deposit)
+		DepositForfeited(AccountId, DomainName, Balance),
+		/// Auction won. (winner, name, price)
+		AuctionWon(AccountId, DomainName, Balance),
+		/// Auction closed without a registration, as the name was restricted or registered meanwhile.
+		/// The highest bid is refunded. (bidder, name, amount)
+		AuctionCancelled(AccountId, DomainName, Balance),
+		/// Domain registered. (owner, name, expire)
+		DomainRegistered(AccountId, DomainName, BlockNumber),
+		/// Domain address updated. (name, addr)
+		AddressUpdated(DomainName, Option<DomainAddr>),
+		/// Domain transferred. (name, from, to)
+		DomainTransferred(DomainName, AccountId, AccountId),
+		/// Domain locked by its owner. (name, until)
+		DomainLocked(DomainName, BlockNumber),
+		/// Domain frozen or unfrozen by root. (name, frozen)
+		DomainFrozen(DomainName, bool),
+		/// Transfer proposed, to be accepted by the recipient. (name, from, to, expire)
+		TransferProposed(DomainName, AccountId, AccountId, Option<BlockNumber>),
+		/// Proposed transfer withdrawn. (name)
+		TransferCancelled(DomainName),
+		/// Account approved for a domain, or approval cleared. (name, owner, approved)
+		Approval(DomainName, AccountId, Option<AccountId>),
+		/// Operator of all domains of an owner allowed or disallowed. (owner, operator, approved)
+		ApprovalForAll(AccountId, AccountId, bool),
+		/// Domain renewed. (name, new_expire)
+		DomainRenewed(DomainName, BlockNumber),
+		/// Domain released after its grace period. (name)
+		DomainExpired(DomainName),
+		/// Subdomain created. (owner, name, expire)
+		SubdomainCreated(AccountId, DomainName, BlockNumber),
+		/// Subdomain and its own subdomains removed by the parent owner. (name)
+		SubdomainRevoked(DomainName),
+		/// Text record set or cleared. (name, key, value)
+		TextChanged(DomainName, Vec<u8>, Option<Vec<u8>>),
+		/// Content hash record set or cleared. (name, hash)
+		ContentHashChanged(DomainName, Option<Vec<u8>>),
+		/// Receiving address for an asset set or cleared. (name, asset_id, addr)
+		AssetAddressChanged(DomainName, AssetId, Option<AccountId>),
+		/// Primary name set or cleared. (account, name)
+		PrimaryNameChanged(AccountId, Option<DomainName>),
+		/// Domain listed for sale. (name, seller, asset_id, price)
+		DomainListed(DomainName, AccountId, AssetId, Balance),
+		/// Domain taken off sale. (name)
+		ListingCancelled(DomainName),
+		/// Domain sold through a listing or an accepted offer. (name, seller, buyer, asset_id, price)
+		DomainSold(DomainName, AccountId, AccountId, AssetId, Balance),
+		/// Offer made for a domain. (name, buyer, asset_id, amount)
+		OfferMade(DomainName, AccountId, AssetId, Balance),
+		/// Offer withdrawn. (name, buyer)
+		OfferWithdrawn(DomainName, AccountId),
+		/// Lease offered. (name, lessor, lessee, duration, asset_id, price)
+		LeaseOffered(DomainName, AccountId, AccountId, BlockNumber, AssetId, Balance),
+		/// Lease offer withdrawn. (name)
+		LeaseOfferCancelled(DomainName),
+		/// Lease accepted, the domain is owned by the lessee until the end block. (name, lessor, lessee, end)
+		DomainLeased(DomainName, AccountId, AccountId, BlockNumber),
+		/// Leased domain returned to its lessor. (name, lessor, lessee)
+		LeaseEnded(DomainName, AccountId, AccountId),
+		/// Name reserved, blocked or released for registration. (name, restriction)
+		RestrictionChanged(DomainName, Option<NameRestriction<AccountId>>),
+		/// Auction proceeds or renewal fee collected. (pooled, burned)
+		RevenueCollected(Balance, Balance),
+		/// Revenue pool paid out to stakers. (amount)
+		RevenueDistributed(Balance),
+	}
+);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::AccountId;
+	use primitives::{Blake2Hasher, H256};
+	use runtime_io::with_externalities;
+	use runtime_primitives::BuildStorage;
+	use runtime_primitives::testing::{Digest, DigestItem, Header};
+	use runtime_primitives::traits::{BlakeTwo256, IdentityLookup, OnFinalize, OnInitialize};
+	use support::{assert_noop, assert_ok, impl_outer_origin};
+
+	impl_outer_origin! {
+		pub enum Origin for Test {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+
+	impl system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Digest = Digest;
+		type AccountId = AccountId;
+		type Lookup = IdentityLookup<AccountId>;
+		type Header = Header;
+		type Event = ();
+		type Log = DigestItem;
+		type Signature = crate::Signature;
+	}
+
+	impl generic_asset::Trait for Test {
+		type Balance = u128;
+		type AssetId = u32;
+		type Event = ();
+	}
+
+	impl cennzx_spot::Trait for Test {
+		type Call = Call<Test>;
+		type Event = ();
+		type ExchangeAddressGenerator = cennzx_spot::ExchangeAddressGenerator<Self>;
+	}
+
+	impl Trait for Test {
+		type Event = ();
+		type PoolAddressGenerator = PoolAddressGenerator<Self>;
+		type OnDomainRemoved = XPay;
+		type OnLeaseEnded = XPay;
+	}
+
+	impl crate::xpay::Trait for Test {
+		type Item = u32;
+		type ItemId = u64;
+		type Event = ();
+	}
+
+	type DomainService = Module<Test>;
+	type GenericAsset = generic_asset::Module<Test>;
+	type XPay = crate::xpay::Module<Test>;
+
+	const SPENDING_ASSET_ID: u32 = 1;
+
+	fn account(seed: u8) -> AccountId {
+		AccountId::unchecked_from(H256::repeat_byte(seed))
+	}
+
+	fn signed(seed: u8) -> Origin {
+		system::RawOrigin::Signed(account(seed)).into()
+	}
+
+	fn root() -> Origin {
+		system::RawOrigin::Root.into()
+	}
+
+	/// Accounts 1 to 3 hold 100 of the settlement asset.
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		t.extend(generic_asset::GenesisConfig::<Test> {
+			assets: vec![SPENDING_ASSET_ID],
+			initial_balance: 100,
+			endowed_accounts: vec![account(1), account(2), account(3)],
+			next_asset_id: 100,
+			create_asset_stake: 0,
+			staking_asset_id: 0,
+			spending_asset_id: SPENDING_ASSET_ID,
+		}.build_storage().unwrap().0);
+		t.extend(GenesisConfig::<Test> {
+			auction_period: 10,
+			extension_window: 3,
+			extension_period: 3,
+			max_extension: 30,
+			commit_period: 10,
+			reveal_period: 10,
+			minimum_bid: 1,
+			minimum_bid_increment: 1,
+			registration_period: 1000,
+			grace_period: 100,
+			price_schedule: vec![10],
+			record_deposit: 1,
+			burn_share: Permill::from_percent(0),
+			domains: vec![],
+			reserved_names: vec![],
+		}.build_storage().unwrap().0);
+		t.into()
+	}
+
+	fn salt(seed: u8) -> H256 {
+		H256::repeat_byte(seed)
+	}
+
+	/// The commitment of account `seed` to bidding `amount` on `name`, salted with `salt(seed)`.
+	fn commitment(seed: u8, name: &DomainName, amount: u128) -> H256 {
+		BlakeTwo256::hash_of(&(&account(seed), name, &amount, &salt(seed)))
+	}
+
+	/// Store `value` under `key` of the `map` storage item with the given prefix, as the baseline
+	/// runtime did.
+	fn put_legacy<K: Encode, V: Encode>(prefix: &[u8], key: &K, value: &V) {
+		unhashed::put(&legacy_key(prefix, key), value);
+	}
+
+	#[test]
+	fn migrates_baseline_domains_listed_in_owners() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let owner = account(1);
+			let raw_name = b"Alice".to_vec();
+			// baseline `DomainDetail { owner, expire, addr }`
+			put_legacy(b"DomainService Domains", &raw_name, &(owner.clone(), 500u64, Some(b"addr".to_vec())));
+			put_legacy(b"DomainService Owners", &owner, &vec![raw_name.clone()]);
+
+			assert_ok!(DomainService::migrate_owners(root(), vec![owner.clone()]));
+
+			let node = DomainService::namehash(b"alice");
+			assert_eq!(DomainService::domains(node), Some(DomainDetail {
+				owner: owner.clone(),
+				expire: 500,
+				addr: Some(b"addr".to_vec()),
+				parent: None,
+			}));
+			assert_eq!(DomainService::name_of(node), Some(b"alice".to_vec()));
+			assert_eq!(DomainService::names_of(&owner, 0, 10), vec![b"alice".to_vec()]);
+			assert_eq!(DomainService::expiries(600), vec![b"alice".to_vec()]);
+			assert!(!legacy_exists(b"DomainService Domains", &raw_name));
+			assert!(!legacy_exists(b"DomainService Owners", &owner));
+		});
+	}
+
+	#[test]
+	fn migrates_baseline_bids_into_escrow() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(20);
+			let bidder = account(1);
+			let name = b"bob".to_vec();
+			// baseline `BidInfo { bid: Bid { bidder, name, amount }, end }`, ended before the upgrade
+			put_legacy(b"DomainService Bids", &name, &((bidder.clone(), name.clone(), 30u128), 15u64));
+
+			assert_ok!(DomainService::migrate_names(root(), vec![name.clone()]));
+
+			let node = DomainService::namehash(&name);
+			let bid_info = DomainService::bids(node).expect("bid is migrated");
+			assert_eq!(bid_info.bid, Bid { bidder: bidder.clone(), amount: 30 });
+			assert_eq!(bid_info.end, 20);
+			assert_eq!(DomainService::auction_ends(20), vec![name.clone()]);
+			assert_eq!(DomainService::auctions(), vec![(name.clone(), bid_info)]);
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &bidder), 30);
+			assert!(!legacy_exists(b"DomainService Bids", &name));
+		});
+	}
+
+	#[test]
+	fn drops_baseline_bids_that_cannot_be_escrowed() {
+		with_externalities(&mut new_test_ext(), || {
+			let name = b"bob".to_vec();
+			put_legacy(b"DomainService Bids", &name, &((account(9), name.clone(), 30u128), 15u64));
+
+			assert_ok!(DomainService::migrate_names(root(), vec![name.clone()]));
+
+			assert_eq!(DomainService::bids(DomainService::namehash(&name)), None);
+			assert!(!legacy_exists(b"DomainService Bids", &name));
+		});
+	}
+
+	#[test]
+	fn keeps_entries_that_do_not_decode() {
+		with_externalities(&mut new_test_ext(), || {
+			let name = b"carol".to_vec();
+			unhashed::put_raw(&legacy_key(b"DomainService Domains", &name), &[1, 2, 3]);
+
+			assert_ok!(DomainService::migrate_names(root(), vec![name.clone()]));
+
+			assert_eq!(DomainService::domains(DomainService::namehash(&name)), None);
+			assert!(legacy_exists(b"DomainService Domains", &name));
+		});
+	}
+
+	#[test]
+	fn rejects_bids_on_names_awaiting_migration() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"alice".to_vec();
+			put_legacy(b"DomainService Domains", &name, &(account(1), 500u64, None::<DomainAddr>));
+
+			assert_noop!(DomainService::bid(signed(2), name.clone(), 10), "domain awaits migration");
+			assert_noop!(DomainService::bid(signed(2), b"ALICE".to_vec(), 10), "domain awaits migration");
+			assert_noop!(
+				DomainService::commit_bid(signed(2), name.clone(), H256::zero(), 10),
+				"domain awaits migration"
+			);
+			assert!(!DomainService::is_available(&name));
+
+			assert_ok!(DomainService::migrate_names(root(), vec![name.clone()]));
+
+			assert_noop!(DomainService::bid(signed(2), name.clone(), 10), "domain already registered");
+			assert_eq!(DomainService::domains(DomainService::namehash(&name)).map(|detail| detail.owner), Some(account(1)));
+		});
+	}
+
+	#[test]
+	fn open_auction_is_settled_from_escrow() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let bidder = account(1);
+			let name = b"dave".to_vec();
+
+			assert_ok!(DomainService::bid(signed(1), name.clone(), 30));
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &bidder), 30);
+
+			<DomainService as OnFinalize<u64>>::on_finalize(11);
+
+			let node = DomainService::namehash(&name);
+			assert_eq!(DomainService::domains(node).map(|detail| (detail.owner, detail.expire)), Some((bidder.clone(), 1011)));
+			assert_eq!(DomainService::bids(node), None);
+			assert!(DomainService::auctions().is_empty());
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &bidder), 0);
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &bidder), 70);
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &DomainService::pool_account()), 30);
+		});
+	}
+
+	#[test]
+	fn outbid_bidder_is_refunded() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"dave".to_vec();
+
+			assert_ok!(DomainService::bid(signed(1), name.clone(), 30));
+			assert_noop!(DomainService::bid(signed(2), name.clone(), 30), "bid amount too small");
+			assert_ok!(DomainService::bid(signed(2), name.clone(), 40));
+
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(1)), 0);
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(1)), 100);
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(2)), 40);
+
+			<DomainService as OnFinalize<u64>>::on_finalize(11);
+
+			let node = DomainService::namehash(&name);
+			assert_eq!(DomainService::domains(node).map(|detail| detail.owner), Some(account(2)));
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(2)), 60);
+		});
+	}
+
+	#[test]
+	fn sealed_auction_winner_pays_the_second_price() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"erin".to_vec();
+
+			assert_ok!(DomainService::commit_bid(signed(1), name.clone(), commitment(1, &name, 40), 50));
+			assert_ok!(DomainService::commit_bid(signed(2), name.clone(), commitment(2, &name, 30), 30));
+			// never revealed
+			assert_ok!(DomainService::commit_bid(signed(3), name.clone(), commitment(3, &name, 35), 40));
+			assert_noop!(DomainService::bid(signed(3), name.clone(), 50), "domain is in a sealed auction");
+
+			system::Module::<Test>::set_block_number(5);
+			assert_noop!(DomainService::reveal_bid(signed(1), name.clone(), 40, salt(1)), "reveal phase has not started");
+
+			system::Module::<Test>::set_block_number(12);
+			assert_noop!(DomainService::reveal_bid(signed(1), name.clone(), 45, salt(1)), "bid does not match commitment");
+			assert_ok!(DomainService::reveal_bid(signed(2), name.clone(), 30, salt(2)));
+			assert_noop!(
+				DomainService::commit_bid(signed(2), name.clone(), commitment(2, &name, 60), 60),
+				"commit phase is over"
+			);
+			assert_ok!(DomainService::reveal_bid(signed(1), name.clone(), 40, salt(1)));
+
+			// the outbid reveal and the excess deposit are refunded right away
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(2)), 0);
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(1)), 40);
+			let sealed = DomainService::bids(DomainService::namehash(&name)).and_then(|bid_info| bid_info.sealed);
+			assert_eq!(sealed.map(|sealed| sealed.second_price), Some(30));
+
+			<DomainService as OnFinalize<u64>>::on_finalize(21);
+
+			let node = DomainService::namehash(&name);
+			assert_eq!(DomainService::domains(node).map(|detail| (detail.owner, detail.expire)), Some((account(1), 1021)));
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(1)), 70);
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(2)), 100);
+			// the unrevealed deposit is forfeited to the pool
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(3)), 0);
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(3)), 60);
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &DomainService::pool_account()), 70);
+			assert_eq!(DomainService::committers(node), Vec::<AccountId>::new());
+		});
+	}
+
+	#[test]
+	fn commitment_can_only_be_revealed_by_its_bidder() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"erin".to_vec();
+
+			assert_ok!(DomainService::commit_bid(signed(1), name.clone(), commitment(1, &name, 40), 40));
+			// a copy of the first bidder's commitment
+			assert_ok!(DomainService::commit_bid(signed(2), name.clone(), commitment(1, &name, 40), 40));
+
+			system::Module::<Test>::set_block_number(12);
+			assert_noop!(DomainService::reveal_bid(signed(2), name.clone(), 40, salt(1)), "bid does not match commitment");
+			assert_ok!(DomainService::reveal_bid(signed(1), name.clone(), 40, salt(1)));
+
+			<DomainService as OnFinalize<u64>>::on_finalize(21);
+
+			let node = DomainService::namehash(&name);
+			assert_eq!(DomainService::domains(node).map(|detail| detail.owner), Some(account(1)));
+			// no second bid was revealed, so the winner pays the minimum bid
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(1)), 99);
+		});
+	}
+
+	#[test]
+	fn expired_domain_is_released_after_the_grace_period() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"frank".to_vec();
+			let node = DomainService::namehash(&name);
+			DomainService::register(&name, account(1), 50, None);
+
+			system::Module::<Test>::set_block_number(51);
+			assert_eq!(DomainService::active_domain(&node), None);
+			assert_noop!(DomainService::update(signed(1), name.clone(), None), "domain expired");
+			// held for the owner during the grace period
+			assert_noop!(DomainService::bid(signed(2), name.clone(), 10), "domain already registered");
+
+			<DomainService as OnFinalize<u64>>::on_finalize(149);
+			assert!(DomainService::domains(node).is_some());
+
+			system::Module::<Test>::set_block_number(150);
+			<DomainService as OnFinalize<u64>>::on_finalize(150);
+			assert_eq!(DomainService::domains(node), None);
+			assert_eq!(DomainService::name_of(node), None);
+			assert!(DomainService::names_of(&account(1), 0, 10).is_empty());
+
+			system::Module::<Test>::set_block_number(151);
+			assert_ok!(DomainService::bid(signed(2), name.clone(), 10));
+		});
+	}
+
+	#[test]
+	fn removing_an_owned_domain_moves_the_last_one_into_its_place() {
+		with_externalities(&mut new_test_ext(), || {
+			let owner = account(1);
+			let nodes: Vec<H256> = ["a", "b", "c"].iter().map(|name| DomainService::namehash(name.as_bytes())).collect();
+			for node in &nodes {
+				DomainService::add_owned_domain(&owner, node);
+			}
+
+			DomainService::remove_owned_domain(&owner, &nodes[0]);
+
+			assert_eq!(DomainService::owned_domains_count(&owner), 2);
+			assert_eq!(DomainService::owned_domain((owner.clone(), 0)), Some(nodes[2]));
+			assert_eq!(DomainService::owned_domain((owner.clone(), 1)), Some(nodes[1]));
+			assert_eq!(<OwnedDomainsIndex<Test>>::get(&nodes[2]), 0);
+			assert_eq!(DomainService::owned_domain((owner.clone(), 2)), None);
+
+			// removing a domain the account does not own changes nothing
+			DomainService::remove_owned_domain(&account(2), &nodes[1]);
+			assert_eq!(DomainService::owned_domains_count(&owner), 2);
+		});
+	}
+
+	#[test]
+	fn names_of_pages_through_the_owner_index() {
+		with_externalities(&mut new_test_ext(), || {
+			for name in &["a", "b", "c"] {
+				DomainService::register(&name.as_bytes().to_vec(), account(1), 100, None);
+			}
+
+			assert_eq!(DomainService::names_of(&account(1), 0, 2), vec![b"a".to_vec(), b"b".to_vec()]);
+			assert_eq!(DomainService::names_of(&account(1), 2, 2), vec![b"c".to_vec()]);
+			assert!(DomainService::names_of(&account(1), 3, 2).is_empty());
+			assert_eq!(DomainService::names_of(&account(1), 1, u64::max_value()).len(), 2);
+
+			assert_ok!(DomainService::transfer(signed(1), b"a".to_vec(), account(2)));
+			assert_eq!(DomainService::names_of(&account(1), 0, 10), vec![b"c".to_vec(), b"b".to_vec()]);
+			assert_eq!(DomainService::names_of(&account(2), 0, 10), vec![b"a".to_vec()]);
+		});
+	}
+
+	#[test]
+	fn parent_owner_manages_subdomains() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let parent = b"acme".to_vec();
+			let name = b"shop.acme".to_vec();
+			let node = DomainService::namehash(&name);
+			DomainService::register(&parent, account(1), 500, None);
+
+			assert_noop!(
+				DomainService::create_subdomain(signed(2), parent.clone(), b"shop".to_vec(), account(2), None),
+				"not owner"
+			);
+			assert_noop!(
+				DomainService::create_subdomain(signed(1), parent.clone(), b"a.shop".to_vec(), account(2), None),
+				"subdomain label contains a separator"
+			);
+			assert_noop!(
+				DomainService::create_subdomain(signed(1), parent.clone(), b"shop".to_vec(), account(2), Some(501)),
+				"subdomain cannot outlive its parent"
+			);
+			assert_ok!(DomainService::create_subdomain(signed(1), parent.clone(), b"Shop".to_vec(), account(2), None));
+			assert_noop!(
+				DomainService::create_subdomain(signed(1), parent.clone(), b"shop".to_vec(), account(3), None),
+				"domain already registered"
+			);
+			assert_noop!(DomainService::bid(signed(3), name.clone(), 10), "subdomains cannot be auctioned");
+
+			let detail = DomainService::domains(node).expect("subdomain is registered");
+			assert_eq!(detail.owner, account(2));
+			assert_eq!(detail.parent, Some(parent.clone()));
+			assert_eq!(DomainService::effective_expire(&detail), 500);
+			assert_eq!(DomainService::subdomains(DomainService::namehash(&parent)), vec![node]);
+
+			// the subdomain owner cannot take it from the parent owner
+			assert_noop!(
+				DomainService::reassign_subdomain(signed(2), name.clone(), account(3), None),
+				"not owner of parent domain"
+			);
+			assert_ok!(DomainService::reassign_subdomain(signed(1), name.clone(), account(3), Some(400)));
+			assert_eq!(DomainService::domains(node).map(|detail| (detail.owner, detail.expire)), Some((account(3), 400)));
+			assert!(DomainService::names_of(&account(2), 0, 10).is_empty());
+
+			assert_ok!(DomainService::create_subdomain(signed(3), name.clone(), b"pay".to_vec(), account(3), None));
+			assert_eq!(DomainService::names_of(&account(3), 0, 10), vec![name.clone(), b"pay.shop.acme".to_vec()]);
+
+			assert_noop!(DomainService::revoke_subdomain(signed(3), name.clone()), "not owner of parent domain");
+			assert_ok!(DomainService::revoke_subdomain(signed(1), name.clone()));
+			assert_eq!(DomainService::domains(node), None);
+			assert_eq!(DomainService::domains(DomainService::namehash(b"pay.shop.acme")), None);
+			assert!(DomainService::names_of(&account(3), 0, 10).is_empty());
+			assert!(DomainService::subdomains(DomainService::namehash(&parent)).is_empty());
+		});
+	}
+
+	#[test]
+	fn subdomains_are_released_with_their_parent() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let parent = b"acme".to_vec();
+			DomainService::register(&parent, account(1), 50, None);
+			assert_ok!(DomainService::create_subdomain(signed(1), parent.clone(), b"shop".to_vec(), account(2), None));
+			let node = DomainService::namehash(b"shop.acme");
+
+			// a subdomain expires with its parent, without a grace period of its own
+			system::Module::<Test>::set_block_number(51);
+			assert_eq!(DomainService::active_domain(&node), None);
+
+			system::Module::<Test>::set_block_number(150);
+			<DomainService as OnFinalize<u64>>::on_finalize(150);
+			assert_eq!(DomainService::domains(DomainService::namehash(&parent)), None);
+			assert_eq!(DomainService::domains(node), None);
+			assert!(DomainService::names_of(&account(2), 0, 10).is_empty());
+		});
+	}
+
+	#[test]
+	fn names_are_validated_and_normalised() {
+		assert_eq!(normalize_name(b"Shop.ACME-1"), Ok(b"shop.acme-1".to_vec()));
+		assert_eq!(normalize_name(b""), Err("domain name is empty"));
+		assert_eq!(normalize_name(b"shop..acme"), Err("domain name has an empty label"));
+		assert_eq!(normalize_name(b"acme."), Err("domain name has an empty label"));
+		assert_eq!(normalize_name(b"-acme"), Err("domain name label starts or ends with '-'"));
+		assert_eq!(normalize_name(b"shop.acme-"), Err("domain name label starts or ends with '-'"));
+		assert_eq!(normalize_name(b"ac_me"), Err("domain name contains an invalid character"));
+		assert_eq!(normalize_name("acmé".as_bytes()), Err("domain name contains an invalid character"));
+		assert_eq!(normalize_name(&[b'a'; MAX_LABEL_LENGTH + 1]), Err("domain name label too long"));
+		assert_eq!(normalize_name(&[b'a'; MAX_NAME_LENGTH + 1]), Err("domain name too long"));
+		assert!(normalize_name(&[b'a'; MAX_LABEL_LENGTH]).is_ok());
+	}
+
+	#[test]
+	fn names_are_hashed_label_by_label() {
+		let acme = DomainService::namehash(b"acme");
+		assert_eq!(acme, DomainService::subnode(&H256::zero(), b"acme"));
+		assert_eq!(DomainService::namehash(b"shop.acme"), DomainService::subnode(&acme, b"shop"));
+		assert_eq!(DomainService::hash_name(b"Shop.Acme"), Ok((b"shop.acme".to_vec(), DomainService::namehash(b"shop.acme"))));
+	}
+
+	#[test]
+	fn differently_cased_names_share_an_auction() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+
+			assert_ok!(DomainService::bid(signed(1), b"Grace".to_vec(), 10));
+			assert_noop!(DomainService::bid(signed(2), b"GRACE".to_vec(), 10), "bid amount too small");
+			assert_noop!(DomainService::bid(signed(2), b"gr ace".to_vec(), 20), "domain name contains an invalid character");
+			assert_eq!(DomainService::name_of(DomainService::namehash(b"grace")), Some(b"grace".to_vec()));
+		});
+	}
+
+	#[test]
+	fn records_hold_a_deposit_until_cleared() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"acme".to_vec();
+			let node = DomainService::namehash(&name);
+			let reserved = || GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(1));
+			DomainService::register(&name, account(1), 500, None);
+
+			assert_noop!(
+				DomainService::set_text(signed(2), name.clone(), b"email".to_vec(), Some(b"a@acme".to_vec())),
+				"not owner or approved"
+			);
+			assert_noop!(
+				DomainService::set_text(signed(1), name.clone(), vec![b'k'; MAX_RECORD_LENGTH + 1], Some(vec![])),
+				"record key too long"
+			);
+
+			assert_ok!(DomainService::set_text(signed(1), name.clone(), b"email".to_vec(), Some(b"a@acme".to_vec())));
+			assert_eq!(reserved(), 1);
+			// replacing a record swaps its deposit
+			assert_ok!(DomainService::set_text(signed(1), name.clone(), b"email".to_vec(), Some(b"b@acme".to_vec())));
+			assert_eq!(reserved(), 1);
+			assert_eq!(DomainService::text_record_keys(node), vec![b"email".to_vec()]);
+			assert_eq!(DomainService::text_record((node, b"email".to_vec())).map(|record| record.value), Some(b"b@acme".to_vec()));
+
+			assert_ok!(DomainService::set_content_hash(signed(1), name.clone(), Some(vec![1; 32])));
+			assert_ok!(DomainService::set_asset_address(signed(1), name.clone(), SPENDING_ASSET_ID, Some(account(2))));
+			assert_eq!(reserved(), 3);
+			assert_eq!(DomainService::asset_address_assets(node), vec![SPENDING_ASSET_ID]);
+
+			assert_ok!(DomainService::set_text(signed(1), name.clone(), b"email".to_vec(), None));
+			assert_eq!(reserved(), 2);
+			assert!(DomainService::text_record_keys(node).is_empty());
+
+			// releasing the domain returns the remaining deposits
+			system::Module::<Test>::set_block_number(600);
+			<DomainService as OnFinalize<u64>>::on_finalize(600);
+			assert_eq!(reserved(), 0);
+			assert_eq!(DomainService::content_hash(node), None);
+			assert_eq!(DomainService::asset_address((node, SPENDING_ASSET_ID)), None);
+			assert!(DomainService::asset_address_assets(node).is_empty());
+		});
+	}
+
+	#[test]
+	fn sealed_auction_price_is_capped_at_the_winning_bid() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"erin".to_vec();
+
+			assert_ok!(DomainService::commit_bid(signed(1), name.clone(), commitment(1, &name, 40), 50));
+			system::Module::<Test>::set_block_number(12);
+			assert_ok!(DomainService::reveal_bid(signed(1), name.clone(), 40, salt(1)));
+			assert_ok!(DomainService::set_minimum_bid(root(), 60, 1));
+
+			<DomainService as OnFinalize<u64>>::on_finalize(21);
+
+			let node = DomainService::namehash(&name);
+			assert_eq!(DomainService::domains(node).map(|detail| detail.owner), Some(account(1)));
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(1)), 0);
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(1)), 60);
+		});
+	}
+
+	#[test]
+	fn auction_for_a_name_reserved_meanwhile_is_refunded() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"dave".to_vec();
+			let node = DomainService::namehash(&name);
+
+			assert_ok!(DomainService::bid(signed(1), name.clone(), 30));
+			assert_ok!(DomainService::reserve_name(root(), name.clone(), None));
+			assert_noop!(DomainService::assign_reserved(root(), name.clone(), account(2), None), "domain is in auction");
+
+			<DomainService as OnFinalize<u64>>::on_finalize(11);
+
+			assert_eq!(DomainService::domains(node), None);
+			assert_eq!(DomainService::name_of(node), None);
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(1)), 0);
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(1)), 100);
+
+			assert_ok!(DomainService::assign_reserved(root(), name.clone(), account(2), None));
+			assert_eq!(DomainService::domains(node).map(|detail| detail.owner), Some(account(2)));
+			assert!(DomainService::names_of(&account(1), 0, 10).is_empty());
+			assert_eq!(DomainService::names_of(&account(2), 0, 10), vec![name.clone()]);
+		});
+	}
+
+	#[test]
+	fn sealed_auction_for_a_name_blocked_meanwhile_is_refunded() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"erin".to_vec();
+
+			assert_ok!(DomainService::commit_bid(signed(1), name.clone(), commitment(1, &name, 40), 50));
+			system::Module::<Test>::set_block_number(12);
+			assert_ok!(DomainService::reveal_bid(signed(1), name.clone(), 40, salt(1)));
+			assert_ok!(DomainService::block_name(root(), name.clone()));
+
+			<DomainService as OnFinalize<u64>>::on_finalize(21);
+
+			assert_eq!(DomainService::domains(DomainService::namehash(&name)), None);
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(1)), 0);
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(1)), 100);
+		});
+	}
+
+	#[test]
+	fn listed_domain_is_sold_at_its_price() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"acme".to_vec();
+			let node = DomainService::namehash(&name);
+			DomainService::register(&name, account(1), 500, None);
+
+			assert_noop!(DomainService::list_domain(signed(2), name.clone(), SPENDING_ASSET_ID, 30), "not owner");
+			assert_ok!(DomainService::list_domain(signed(1), name.clone(), SPENDING_ASSET_ID, 30));
+			assert_noop!(DomainService::cancel_listing(signed(2), name.clone()), "not seller");
+			assert_noop!(
+				DomainService::buy_domain(signed(1), name.clone(), SPENDING_ASSET_ID, 30),
+				"cannot buy own domain"
+			);
+			assert_noop!(
+				DomainService::buy_domain(signed(2), name.clone(), SPENDING_ASSET_ID, 20),
+				"price exceeds max paying amount"
+			);
+
+			assert_ok!(DomainService::buy_domain(signed(2), name.clone(), SPENDING_ASSET_ID, 30));
+			assert_eq!(DomainService::domains(node).map(|detail| detail.owner), Some(account(2)));
+			assert_eq!(DomainService::listing(node), None);
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(1)), 130);
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(2)), 70);
+			assert_noop!(
+				DomainService::buy_domain(signed(3), name.clone(), SPENDING_ASSET_ID, 30),
+				"domain is not for sale"
+			);
+		});
+	}
+
+	#[test]
+	fn accepted_offer_is_paid_from_reserve() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"acme".to_vec();
+			let node = DomainService::namehash(&name);
+			DomainService::register(&name, account(1), 500, None);
+
+			assert_noop!(DomainService::make_offer(signed(1), name.clone(), SPENDING_ASSET_ID, 40), "cannot buy own domain");
+			assert_ok!(DomainService::make_offer(signed(2), name.clone(), SPENDING_ASSET_ID, 40));
+			assert_noop!(DomainService::make_offer(signed(2), name.clone(), SPENDING_ASSET_ID, 50), "offer already made");
+			assert_ok!(DomainService::make_offer(signed(3), name.clone(), SPENDING_ASSET_ID, 50));
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(2)), 40);
+
+			assert_noop!(DomainService::accept_offer(signed(2), name.clone(), account(3)), "not owner");
+			assert_ok!(DomainService::accept_offer(signed(1), name.clone(), account(2)));
+			assert_eq!(DomainService::domains(node).map(|detail| detail.owner), Some(account(2)));
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(1)), 140);
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(2)), 0);
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(2)), 60);
+
+			// other offers stay until withdrawn
+			assert_ok!(DomainService::withdraw_offer(signed(3), name.clone()));
+			assert_eq!(GenericAsset::free_balance(&SPENDING_ASSET_ID, &account(3)), 100);
+			assert_noop!(DomainService::withdraw_offer(signed(3), name.clone()), "no offer");
+		});
+	}
+
+	#[test]
+	fn offer_without_its_reserve_cannot_be_accepted() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"acme".to_vec();
+			DomainService::register(&name, account(1), 500, None);
+
+			assert_ok!(DomainService::make_offer(signed(2), name.clone(), SPENDING_ASSET_ID, 40));
+			GenericAsset::unreserve(&SPENDING_ASSET_ID, &account(2), 10);
+
+			assert_noop!(DomainService::accept_offer(signed(1), name.clone(), account(2)), "offer is not fully reserved");
+			assert_eq!(DomainService::domains(DomainService::namehash(&name)).map(|detail| detail.owner), Some(account(1)));
+		});
+	}
+
+	#[test]
+	fn approved_account_manages_and_transfers_a_domain() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"acme".to_vec();
+			let node = DomainService::namehash(&name);
+			DomainService::register(&name, account(1), 500, None);
+
+			assert_noop!(DomainService::approve(signed(3), name.clone(), Some(account(2))), "not owner or operator");
+			assert_noop!(DomainService::update(signed(2), name.clone(), None), "not owner or approved");
+			assert_ok!(DomainService::approve(signed(1), name.clone(), Some(account(2))));
+
+			assert_ok!(DomainService::update(signed(2), name.clone(), Some(b"addr".to_vec())));
+			assert_ok!(DomainService::transfer_from(signed(2), name.clone(), account(3)));
+			assert_eq!(DomainService::domains(node).map(|detail| detail.owner), Some(account(3)));
+			// the approval is cleared when the domain changes hands
+			assert_eq!(DomainService::approved(node), None);
+			assert_noop!(DomainService::transfer_from(signed(2), name.clone(), account(2)), "not owner or approved");
+		});
+	}
+
+	#[test]
+	fn operator_manages_all_domains_of_an_owner() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"acme".to_vec();
+			DomainService::register(&name, account(1), 500, None);
+
+			assert_noop!(DomainService::set_approval_for_all(signed(1), account(1), true), "cannot approve self");
+			assert_ok!(DomainService::set_approval_for_all(signed(1), account(2), true));
+			assert!(DomainService::is_operator((account(1), account(2))));
+
+			assert_ok!(DomainService::set_text(signed(2), name.clone(), b"url".to_vec(), Some(b"acme.io".to_vec())));
+			// the record deposit is reserved from the operator
+			assert_eq!(GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(2)), 1);
+			assert_ok!(DomainService::approve(signed(2), name.clone(), Some(account(3))));
+			assert_eq!(DomainService::approved(DomainService::namehash(&name)), Some(account(3)));
+
+			assert_ok!(DomainService::set_approval_for_all(signed(1), account(2), false));
+			assert_noop!(DomainService::update(signed(2), name.clone(), None), "not owner or approved");
+			assert_ok!(DomainService::update(signed(3), name.clone(), None));
+		});
+	}
+
+	#[test]
+	fn proposed_transfer_completes_when_accepted() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"acme".to_vec();
+			let node = DomainService::namehash(&name);
+			DomainService::register(&name, account(1), 500, None);
+
+			assert_noop!(DomainService::propose_transfer(signed(1), name.clone(), account(1), None), "cannot transfer to self");
+			assert_ok!(DomainService::propose_transfer(signed(1), name.clone(), account(2), Some(10)));
+			// nothing changes hands until the recipient accepts
+			assert_eq!(DomainService::domains(node).map(|detail| detail.owner), Some(account(1)));
+			assert_noop!(DomainService::accept_transfer(signed(3), name.clone()), "transfer not proposed to sender");
+
+			system::Module::<Test>::set_block_number(11);
+			assert_noop!(DomainService::accept_transfer(signed(2), name.clone()), "transfer proposal expired");
+
+			assert_ok!(DomainService::propose_transfer(signed(1), name.clone(), account(2), None));
+			assert_ok!(DomainService::accept_transfer(signed(2), name.clone()));
+			assert_eq!(DomainService::domains(node).map(|detail| detail.owner), Some(account(2)));
+			assert_eq!(DomainService::pending_transfer(node), None);
+			assert_eq!(DomainService::names_of(&account(2), 0, 10), vec![name.clone()]);
+		});
+	}
+
+	#[test]
+	fn proposed_transfer_can_be_cancelled_or_lapse() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"acme".to_vec();
+			DomainService::register(&name, account(1), 500, None);
+
+			assert_ok!(DomainService::propose_transfer(signed(1), name.clone(), account(2), None));
+			assert_noop!(DomainService::cancel_transfer(signed(3), name.clone()), "not party to transfer");
+			assert_ok!(DomainService::cancel_transfer(signed(2), name.clone()));
+			assert_noop!(DomainService::accept_transfer(signed(2), name.clone()), "no pending transfer");
+
+			// a proposal is dropped when the domain changes hands otherwise
+			assert_ok!(DomainService::propose_transfer(signed(1), name.clone(), account(2), None));
+			assert_ok!(DomainService::transfer(signed(1), name.clone(), account(3)));
+			assert_noop!(DomainService::accept_transfer(signed(2), name.clone()), "no pending transfer");
+		});
+	}
+
+	#[test]
+	fn locked_domain_cannot_be_changed_until_the_lock_ends() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"acme".to_vec();
+			let node = DomainService::namehash(&name);
+			DomainService::register(&name, account(1), 500, None);
+
+			assert_noop!(DomainService::lock(signed(2), name.clone(), 10), "not owner");
+			assert_ok!(DomainService::lock(signed(1), name.clone(), 10));
+			assert_noop!(DomainService::lock(signed(1), name.clone(), 5), "lock can only be extended");
+			assert_noop!(DomainService::update(signed(1), name.clone(), None), "domain is locked");
+			assert_noop!(DomainService::transfer(signed(1), name.clone(), account(2)), "domain is locked");
+			assert_noop!(
+				DomainService::set_text(signed(1), name.clone(), b"url".to_vec(), Some(b"acme.io".to_vec())),
+				"domain is locked"
+			);
+
+			system::Module::<Test>::set_block_number(11);
+			assert_ok!(DomainService::update(signed(1), name.clone(), None));
+
+			// locks end at the expiry of the domain at the latest
+			assert_ok!(DomainService::lock(signed(1), name.clone(), u64::max_value()));
+			assert_eq!(DomainService::locked_until(node), Some(500));
+		});
+	}
+
+	#[test]
+	fn frozen_domain_cannot_be_changed_until_unfrozen() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"acme".to_vec();
+			DomainService::register(&name, account(1), 500, None);
+
+			assert_ok!(DomainService::freeze_domain(root(), name.clone()));
+			assert_noop!(DomainService::update(signed(1), name.clone(), None), "domain is frozen");
+			assert_noop!(DomainService::transfer(signed(1), name.clone(), account(2)), "domain is frozen");
+
+			assert_ok!(DomainService::unfreeze_domain(root(), name.clone()));
+			assert_ok!(DomainService::transfer(signed(1), name.clone(), account(2)));
+		});
+	}
+
+	#[test]
+	fn subdomain_lock_does_not_block_the_parent_owner() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let parent = b"acme".to_vec();
+			let name = b"shop.acme".to_vec();
+			let node = DomainService::namehash(&name);
+			DomainService::register(&parent, account(1), 500, None);
+			assert_ok!(DomainService::create_subdomain(signed(1), parent.clone(), b"shop".to_vec(), account(2), None));
+
+			assert_ok!(DomainService::lock(signed(2), name.clone(), u64::max_value()));
+			assert_eq!(DomainService::locked_until(node), Some(500));
+			assert_noop!(DomainService::update(signed(2), name.clone(), None), "domain is locked");
+
+			assert_ok!(DomainService::reassign_subdomain(signed(1), name.clone(), account(3), None));
+			assert_eq!(DomainService::locked_until(node), None);
+			assert_ok!(DomainService::update(signed(3), name.clone(), None));
+
+			// only a root freeze stops the parent owner
+			assert_ok!(DomainService::freeze_domain(root(), name.clone()));
+			assert_noop!(DomainService::revoke_subdomain(signed(1), name.clone()), "domain is frozen");
+			assert_ok!(DomainService::unfreeze_domain(root(), name.clone()));
+			assert_ok!(DomainService::revoke_subdomain(signed(1), name.clone()));
+		});
+	}
+
+	#[test]
+	fn lease_returns_the_domain_without_what_the_lessee_set() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"acme".to_vec();
+			let node = DomainService::namehash(&name);
+			let reserved = |seed| GenericAsset::reserved_balance(&SPENDING_ASSET_ID, &account(seed));
+			DomainService::register(&name, account(1), 500, Some(b"home".to_vec()));
+			assert_ok!(DomainService::set_text(signed(1), name.clone(), b"url".to_vec(), Some(b"acme.io".to_vec())));
+			assert_ok!(XPay::create_item(signed(1), name.clone(), 1, 7, SPENDING_ASSET_ID, 5));
+
+			assert_ok!(DomainService::offer_lease(signed(1), name.clone(), account(2), 100, SPENDING_ASSET_ID, 10));
+			assert_ok!(DomainService::accept_lease(signed(2), name.clone(), SPENDING_ASSET_ID, 10));
+			assert_eq!(DomainService::lease(node).map(|lease| lease.end), Some(101));
+
+			assert_ok!(DomainService::update(signed(2), name.clone(), Some(b"shop".to_vec())));
+			assert_ok!(DomainService::set_text(signed(2), name.clone(), b"email".to_vec(), Some(b"b@acme".to_vec())));
+			assert_ok!(XPay::create_item(signed(2), name.clone(), 1, 8, SPENDING_ASSET_ID, 5));
+			assert_eq!(reserved(2), 1);
+			assert_noop!(DomainService::transfer(signed(2), name.clone(), account(3)), "domain is leased");
+			assert_noop!(DomainService::lock(signed(2), name.clone(), 50), "domain is leased");
+			assert_noop!(
+				DomainService::create_subdomain(signed(2), name.clone(), b"shop".to_vec(), account(3), None),
+				"domain is leased"
+			);
+
+			system::Module::<Test>::set_block_number(101);
+			<DomainService as OnInitialize<u64>>::on_initialize(101);
+
+			let domain_detail = DomainService::domains(node).expect("domain is kept");
+			assert_eq!(domain_detail.owner, account(1));
+			assert_eq!(domain_detail.addr, Some(b"home".to_vec()));
+			assert_eq!(DomainService::lease(node), None);
+			assert_eq!(DomainService::text_record_keys(node), vec![b"url".to_vec()]);
+			assert_eq!(reserved(1), 1);
+			assert_eq!(reserved(2), 0);
+			assert_eq!(XPay::domain_items(node), vec![0]);
+			assert_eq!(XPay::item(1), None);
+			assert_eq!(DomainService::names_of(&account(1), 0, 10), vec![name.clone()]);
+			assert!(DomainService::names_of(&account(2), 0, 10).is_empty());
+			assert_ok!(DomainService::transfer(signed(1), name.clone(), account(3)));
+		});
+	}
+
+	#[test]
+	fn lease_cannot_run_past_domain_expiry() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let name = b"acme".to_vec();
+			DomainService::register(&name, account(1), 500, None);
+
+			assert_ok!(DomainService::offer_lease(signed(1), name.clone(), account(2), 500, SPENDING_ASSET_ID, 10));
+			assert_noop!(
+				DomainService::accept_lease(signed(3), name.clone(), SPENDING_ASSET_ID, 10),
+				"lease not offered to sender"
+			);
+			assert_noop!(
+				DomainService::accept_lease(signed(2), name.clone(), SPENDING_ASSET_ID, 10),
+				"lease runs past domain expiry"
+			);
+		});
+	}
+}
@@ -37,6 +37,7 @@ pub use contract::Schedule;
 pub use staking::StakerStatus;
 
 pub use cennzx_spot::{ExchangeAddressGenerator, FeeRate};
+pub use runtime_primitives::Permill;
 
 pub use fees;
 pub use generic_asset;
@@ -45,6 +46,18 @@ mod fee;
 
 mod domain_service;
 mod xpay;
+mod cns_api;
+mod revenue;
+
+pub use domain_service::{BidInfo, DomainDetail, DomainName};
+pub use cns_api::{DomainServiceApi, XPayApi};
+
+/// Identifier of a generic asset.
+pub type AssetId = u32;
+/// An item sold through XPay.
+pub type Item = u32;
+/// Identifier of an item sold through XPay.
+pub type ItemId = u64;
 
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
 /// the specifics of the runtime. They can then be made to be agnostic over specific formats
@@ -83,7 +96,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("cennznet-runtime-template"),
 	impl_name: create_runtime_str!("cennznet-runtime-template"),
 	authoring_version: 3,
-	spec_version: 3,
+	spec_version: 4,
 	impl_version: 0,
 	apis: RUNTIME_API_VERSIONS,
 };
@@ -191,7 +204,7 @@ impl staking::Trait for Runtime {
 	type OnRewardMinted = ();
 	type Event = Event;
 	type Slash = ();
-	type Reward = ();
+	type Reward = revenue::StakerRevenue;
 }
 
 impl grandpa::Trait for Runtime {
@@ -219,7 +232,7 @@ impl sudo::Trait for Runtime {
 
 impl generic_asset::Trait for Runtime {
 	type Balance = u128;
-	type AssetId = u32;
+	type AssetId = AssetId;
 	type Event = Event;
 }
 
@@ -239,11 +252,14 @@ impl cennzx_spot::Trait for Runtime {
 
 impl domain_service::Trait for Runtime {
 	type Event = Event;
+	type PoolAddressGenerator = domain_service::PoolAddressGenerator<Self>;
+	type OnDomainRemoved = XPay;
+	type OnLeaseEnded = XPay;
 }
 
 impl xpay::Trait for Runtime {
-	type Item = u32;
-	type ItemId = u64;
+	type Item = Item;
+	type ItemId = ItemId;
 	type Event = Event;
 }
 
@@ -266,7 +282,7 @@ construct_runtime!(
 		Sudo: sudo,
 		Fees: fees::{Module, Call, Fee, Storage, Config<T>, Event<T>},
 		CennzxSpot: cennzx_spot::{Module, Call, Storage, Config<T>, Event<T>},
-		DomainService: domain_service::{Module, Call, Storage, Event<T>},
+		DomainService: domain_service::{Module, Call, Storage, Config<T>, Event<T>},
 		XPay: xpay::{Module, Call, Storage, Event<T>},
 	}
 );
@@ -395,4 +411,40 @@ impl_runtime_apis! {
 			Consensus::authorities()
 		}
 	}
+
+	impl cns_api::DomainServiceApi<Block> for Runtime {
+		fn resolve(name: DomainName) -> Option<AccountId> {
+			DomainService::hash_name(&name).ok().and_then(|(_, node)| DomainService::resolve(&node))
+		}
+
+		fn domain_detail(name: DomainName) -> Option<DomainDetail<AccountId, BlockNumber>> {
+			DomainService::hash_name(&name).ok().and_then(|(_, node)| DomainService::domains(node))
+		}
+
+		fn names_of(account: AccountId, start: u64, count: u64) -> Vec<DomainName> {
+			DomainService::names_of(&account, start, count)
+		}
+
+		fn bid_of(name: DomainName) -> Option<BidInfo<AccountId, Balance, BlockNumber>> {
+			DomainService::hash_name(&name).ok().and_then(|(_, node)| DomainService::bids(node))
+		}
+
+		fn is_available(name: DomainName) -> bool {
+			domain_service::normalize_name(&name).map(|name| DomainService::is_available(&name)).unwrap_or(false)
+		}
+
+		fn reverse(account: AccountId) -> Option<DomainName> {
+			DomainService::reverse(&account)
+		}
+
+		fn auctions() -> Vec<(DomainName, BidInfo<AccountId, Balance, BlockNumber>)> {
+			DomainService::auctions()
+		}
+	}
+
+	impl cns_api::XPayApi<Block> for Runtime {
+		fn items_of(name: DomainName) -> Vec<(ItemId, Item, u32, (AssetId, Balance))> {
+			DomainService::hash_name(&name).map(|(_, node)| XPay::items_of(&node)).unwrap_or_default()
+		}
+	}
 }
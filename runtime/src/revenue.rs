@@ -0,0 +1,51 @@
+//!
+//! Payout of the CNS revenue pool to stakers
+//!
+use crate::{AccountId, Balance, DomainService, GenericAsset, Runtime, Staking};
+use generic_asset::SpendingAssetCurrency;
+use rstd::prelude::*;
+use runtime_primitives::Perbill;
+use support::traits::{Currency, OnUnbalanced};
+
+type PositiveImbalanceOf = <SpendingAssetCurrency<Runtime> as Currency<AccountId>>::PositiveImbalance;
+
+/// Staking reward handler paying the revenue pool out on top of the era rewards.
+///
+/// Staking hands over the rewards it minted once it has paid them for the era. The pool is then paid
+/// out to the validators elected for the era and their nominators, pro rata to their stake in the
+/// validators' exposures.
+pub struct StakerRevenue;
+
+impl OnUnbalanced<PositiveImbalanceOf> for StakerRevenue {
+	fn on_unbalanced(_: PositiveImbalanceOf) {
+		// the era rewards stay minted, the pool comes on top of them
+		let asset_id = GenericAsset::spending_asset_id();
+		let pool = DomainService::pool_account();
+		let amount = GenericAsset::free_balance(&asset_id, &pool);
+		if amount == 0 {
+			return;
+		}
+
+		let stakes: Vec<(AccountId, Balance)> = Staking::current_elected()
+			.into_iter()
+			.flat_map(|validator| {
+				let exposure = Staking::stakers(&validator);
+				let others = exposure.others.into_iter().map(|nominator| (nominator.who, nominator.value));
+				Some((validator, exposure.own)).into_iter().chain(others)
+			})
+			.filter(|(_, stake)| *stake > 0)
+			.collect();
+		let total_stake = stakes.iter().fold(0, |total: Balance, (_, stake)| total.saturating_add(*stake));
+
+		let mut paid: Balance = 0;
+		for (who, stake) in stakes {
+			let share = Perbill::from_rational_approximation(stake, total_stake) * amount;
+			if share > 0 && GenericAsset::make_transfer(&asset_id, &pool, &who, share).is_ok() {
+				paid += share;
+			}
+		}
+		if paid > 0 {
+			DomainService::note_distributed(paid);
+		}
+	}
+}
@@ -1,6 +1,6 @@
 use support::{decl_module, decl_storage, decl_event, StorageValue, StorageMap, dispatch::Result, Parameter, ensure};
 use runtime_primitives::traits::{CheckedAdd, CheckedMul, As};
-use system::ensure_signed;
+use system::{ensure_signed, ensure_root};
 use rstd::result;
 use rstd::vec::Vec;
 use crate::domain_service::{self, DomainName};
@@ -19,9 +19,14 @@ pub type PriceOf<T> = (AssetIdOf<T>, BalanceOf<T>);
 decl_storage! {
 	trait Store for Module<T: Trait> as XPay {
 		pub Items get(item): map T::ItemId => Option<T::Item>;
-		pub ItemOwners get(item_owner): map T::ItemId => Option<DomainName>;
+		/// Name hash of the domain selling an item.
+		pub ItemOwners get(item_owner): map T::ItemId => Option<T::Hash>;
+		/// Account that created an item.
+		pub ItemCreators get(item_creator): map T::ItemId => Option<T::AccountId>;
 		pub ItemQuantities get(item_quantity): map T::ItemId => u32;
 		pub ItemPrices get(item_price): map T::ItemId => Option<PriceOf<T>>;
+		/// Items created for a domain, by name hash.
+		pub DomainItems get(domain_items): map T::Hash => Vec<T::ItemId>;
 		
 		pub NextItemId get(next_item_id): T::ItemId;
 	}
@@ -36,7 +41,9 @@ decl_module! {
 
 			let item_id = Self::next_item_id();
 
+			let (_, domain) = domain_service::Module::<T>::hash_name(&domain)?;
 			Self::ensure_ownwer(&domain, &origin)?;
+			domain_service::Module::<T>::ensure_unlocked(&domain)?;
 
 			// The last available id serves as the overflow mark and won't be used.
 			let next_item_id = item_id.checked_add(&1.into()).ok_or_else(||"No new item id is available.")?;
@@ -46,7 +53,9 @@ decl_module! {
 			let price = (price_asset_id, price_amount);
 
 			<Items<T>>::insert(item_id.clone(), item.clone());
-			<ItemOwners<T>>::insert(item_id.clone(), domain);
+			<ItemOwners<T>>::insert(item_id.clone(), &domain);
+			<ItemCreators<T>>::insert(item_id.clone(), &origin);
+			<DomainItems<T>>::mutate(domain, |items| items.push(item_id.clone()));
 			<ItemQuantities<T>>::insert(item_id.clone(), quantity);
 			<ItemPrices<T>>::insert(item_id.clone(), price.clone());
 
@@ -117,8 +126,35 @@ decl_module! {
 			to_amount: BalanceOf<T>
 		) {
 			let origin = ensure_signed(origin)?;
+			let (_, to_domain) = domain_service::Module::<T>::hash_name(&to_domain)?;
 			Self::make_transfer(&origin, from_asset, from_amount, &to_domain, to_asset, to_amount)?;
 		}
+
+		/// Move the owners of `item_ids` from the baseline plain domain names to name hashes. Items
+		/// already owned by a name hash, or owned by a name that is not valid, are left as they are.
+		pub fn migrate_items(origin, item_ids: Vec<T::ItemId>) -> Result {
+			ensure_root(origin)?;
+
+			for item_id in item_ids {
+				// the key is unchanged, so tell migrated items apart by their domain's item list
+				if let Some(node) = Self::item_owner(&item_id) {
+					if Self::domain_items(&node).contains(&item_id) {
+						continue;
+					}
+				}
+
+				let domain = match domain_service::get_legacy::<_, DomainName>(b"XPay ItemOwners", &item_id) {
+					Some(domain) => domain,
+					None => continue,
+				};
+				if let Ok((_, node)) = domain_service::Module::<T>::hash_name(&domain) {
+					<ItemOwners<T>>::insert(&item_id, &node);
+					<DomainItems<T>>::mutate(&node, |items| items.push(item_id.clone()));
+				}
+			}
+
+			Ok(())
+		}
 	}
 }
 
@@ -143,29 +179,55 @@ decl_event!(
 );
 
 impl<T: Trait> Module<T> {
-	fn ensure_ownwer(domain: &DomainName, owner: &T::AccountId) -> Result {
-		let detail = domain_service::Module::<T>::domains(domain).ok_or_else(|| "Domain not exist")?;
+	/// The items of `domain` with their remaining quantity and price.
+	pub fn items_of(domain: &T::Hash) -> Vec<(T::ItemId, T::Item, u32, PriceOf<T>)> {
+		Self::domain_items(domain)
+			.into_iter()
+			.filter_map(|item_id| {
+				let item = Self::item(&item_id)?;
+				let price = Self::item_price(&item_id)?;
+				Some((item_id.clone(), item, Self::item_quantity(&item_id), price))
+			})
+			.collect()
+	}
+
+	fn ensure_ownwer(domain: &T::Hash, owner: &T::AccountId) -> Result {
+		let detail = domain_service::Module::<T>::active_domain(domain).ok_or_else(|| "Domain not exist")?;
 		if *owner == detail.owner {
 			return Ok(());
 		}
 		return Err("Not owner");
 	}
 
-	fn resolve_domain(domain: &DomainName) -> result::Result<T::AccountId, &'static str> {
-		let detail = domain_service::Module::<T>::domains(domain).ok_or_else(|| "Domain not exist")?;
+	/// The account receiving `asset_id` sent to `domain`: the domain's address for that asset if it
+	/// has one, otherwise its general address.
+	fn resolve_domain(domain: &T::Hash, asset_id: &AssetIdOf<T>) -> result::Result<T::AccountId, &'static str> {
+		let detail = domain_service::Module::<T>::active_domain(domain).ok_or_else(|| "Domain not exist")?;
+		if let Some(record) = domain_service::Module::<T>::asset_address((*domain, asset_id.clone())) {
+			return Ok(record.value);
+		}
 		let addr = detail.addr.ok_or_else(|| "Domain not published")?;
 		Decode::decode(&mut &addr[..]).ok_or_else(|| "Not address")
 	}
 
+	/// Remove `item_id` from storage, apart from the item list of its domain.
+	fn drop_item(item_id: &T::ItemId) {
+		<Items<T>>::remove(item_id);
+		<ItemOwners<T>>::remove(item_id);
+		<ItemCreators<T>>::remove(item_id);
+		<ItemQuantities<T>>::remove(item_id);
+		<ItemPrices<T>>::remove(item_id);
+	}
+
 	fn make_transfer(
 		from: &T::AccountId,
 		from_asset: AssetIdOf<T>,
 		from_amount: BalanceOf<T>,
-		to_domain: &DomainName,
+		to_domain: &T::Hash,
 		to_asset: AssetIdOf<T>,
 		to_amount: BalanceOf<T>
 	) -> Result {
-		let to_account = Self::resolve_domain(to_domain)?;
+		let to_account = Self::resolve_domain(to_domain, &to_asset)?;
 		if from_asset == to_asset {
 			// Same asset, GA transfer
 
@@ -186,4 +248,29 @@ impl<T: Trait> Module<T> {
 
 		Ok(())
 	}
-}
\ No newline at end of file
+}
+
+/// Drops the storefront of released domains, so that it is not inherited by the next owner.
+impl<T: Trait> domain_service::OnDomainRemoved<T::Hash> for Module<T> {
+	fn on_domain_removed(domain: &T::Hash) {
+		for item_id in <DomainItems<T>>::take(domain) {
+			Self::drop_item(&item_id);
+		}
+	}
+}
+
+/// Drops the storefront items the lessee created for a leased domain, so that they are not taken
+/// over by the lessor.
+impl<T: Trait> domain_service::OnLeaseEnded<T::Hash, T::AccountId> for Module<T> {
+	fn on_lease_ended(domain: &T::Hash, lessee: &T::AccountId) {
+		let (created, kept): (Vec<_>, Vec<_>) = <DomainItems<T>>::take(domain)
+			.into_iter()
+			.partition(|item_id| Self::item_creator(item_id).as_ref() == Some(lessee));
+		for item_id in created {
+			Self::drop_item(&item_id);
+		}
+		if !kept.is_empty() {
+			<DomainItems<T>>::insert(domain, kept);
+		}
+	}
+}
@@ -1,8 +1,10 @@
 use cennznet_runtime_template_runtime::{
-    fees, generic_asset, AccountId, CennzxSpotConfig, ConsensusConfig, ContractConfig, Fee,
-    FeeRate, FeesConfig, GenericAssetConfig, GenesisConfig, GrandpaConfig, IndicesConfig, Schedule,
-    SessionConfig, StakerStatus, StakingConfig, SudoConfig, TimestampConfig,
+    fees, generic_asset, AccountId, CennzxSpotConfig, ConsensusConfig, ContractConfig,
+    DomainServiceConfig, Fee, FeeRate, FeesConfig, GenericAssetConfig, GenesisConfig, GrandpaConfig,
+    IndicesConfig, Permill, Schedule, SessionConfig, StakerStatus, StakingConfig, SudoConfig,
+    TimestampConfig,
 };
+use parity_codec::Encode;
 use primitives::{ed25519, sr25519, Pair};
 use substrate_service;
 
@@ -178,6 +180,29 @@ fn testnet_genesis(
 			fee_rate: FeeRate::from_milli(3),
 			core_asset_id: 16001,
 		}),
+		domain_service: Some(DomainServiceConfig {
+			auction_period: 10,
+			extension_window: 3,
+			extension_period: 3,
+			max_extension: 30,
+			commit_period: 10,
+			reveal_period: 10,
+			minimum_bid: 1,
+			minimum_bid_increment: 1,
+			registration_period: 1000,
+			grace_period: 100,
+			price_schedule: vec![1_000, 500, 100, 10],
+			record_deposit: 1,
+			burn_share: Permill::from_percent(10),
+			domains: vec![("alice", "Alice"), ("bob", "Bob")]
+				.into_iter()
+				.map(|(name, seed)| {
+					let owner = get_account_id_from_seed(seed);
+					(name.as_bytes().to_vec(), owner.clone(), Some(owner.encode()), 1_000_000)
+				})
+				.collect(),
+			reserved_names: vec![b"cennznet".to_vec(), b"centrality".to_vec()],
+		}),
 		sudo: Some(SudoConfig {
 			key: root_key,
 		}),
@@ -1,14 +1,27 @@
 use crate::chain_spec;
+use crate::cns::{self, CustomCommand};
+use crate::rpc;
 use crate::service;
 use futures::{future, sync::oneshot, Future};
 use log::info;
 use std::cell::RefCell;
 use std::ops::Deref;
+use structopt::StructOpt;
 pub use substrate_cli::{error, IntoExit, VersionInfo};
-use substrate_cli::{informant, parse_and_execute, NoCustom};
+use substrate_cli::{impl_augment_clap, informant, parse_and_execute};
 use substrate_service::{Roles as ServiceRoles, ServiceFactory};
 use tokio::runtime::Runtime;
 
+/// Additional arguments of the node.
+#[derive(Debug, StructOpt, Clone)]
+pub struct NodeParams {
+    /// Specify the port of the `cns` HTTP RPC server, served on the interface of the HTTP RPC.
+    #[structopt(long = "cns-rpc-port", value_name = "PORT", default_value = "9935")]
+    pub cns_rpc_port: u16,
+}
+
+impl_augment_clap!(NodeParams);
+
 /// Parse command line arguments into service configuration.
 pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 where
@@ -16,13 +29,13 @@ where
     T: Into<std::ffi::OsString> + Clone,
     E: IntoExit,
 {
-    parse_and_execute::<service::Factory, NoCustom, NoCustom, _, _, _, _, _>(
+    parse_and_execute::<service::Factory, CustomCommand, NodeParams, _, _, _, _, _>(
         load_spec,
         &version,
         "cennznet-runtime-template-node",
         args,
         exit,
-        |exit, _cli_args, _custom_args, config| {
+        |exit, _cli_args, node_params, config| {
             info!("{}", version.name);
             info!("  version {}", config.full_version());
             info!("  by {}, 2019", version.author);
@@ -38,17 +51,36 @@ where
                         .map_err(|e| format!("{:?}", e))?,
                     exit,
                 ),
-                _ => run_until_exit(
-                    runtime,
-                    service::Factory::new_full(config, executor).map_err(|e| format!("{:?}", e))?,
-                    exit,
-                ),
+                _ => {
+                    let cns_rpc_addr = config.rpc_http.map(|mut addr| {
+                        addr.set_port(node_params.cns_rpc_port);
+                        addr
+                    });
+                    let rpc_cors = config.rpc_cors.clone();
+                    let service = service::Factory::new_full(config, executor)
+                        .map_err(|e| format!("{:?}", e))?;
+                    // kept alive until the node exits
+                    let _cns_rpc = match cns_rpc_addr {
+                        Some(addr) => {
+                            info!("CNS RPC listening on {}", addr);
+                            Some(
+                                rpc::start_http(&addr, rpc_cors.as_ref(), service.client())
+                                    .map_err(|e| format!("{:?}", e))?,
+                            )
+                        }
+                        None => None,
+                    };
+                    run_until_exit(runtime, service, exit)
+                }
             }
             .map_err(|e| format!("{:?}", e))
         },
     )
     .map_err(Into::into)
-    .map(|_| ())
+    .and_then(|custom| match custom {
+        Some(CustomCommand::Cns(cmd)) => cns::run(cmd, load_spec, &version),
+        None => Ok(()),
+    })
 }
 
 fn load_spec(id: &str) -> Result<Option<chain_spec::ChainSpec>, String> {
@@ -0,0 +1,311 @@
+//! The `cns` subcommand, querying the CNS registry straight from the node's database.
+//!
+//! The client runs on a scratch snapshot of the node's database, kept next to it under the base
+//! path, so the database itself is never written to. It is meant to be used on a stopped node,
+//! without starting RPC.
+
+use crate::chain_spec;
+use crate::service::Factory;
+use cennznet_runtime_template_runtime::{
+    opaque::BlockId, AccountId, DomainName, DomainServiceApi, XPayApi,
+};
+use primitives::crypto::Ss58Codec;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use substrate_cli::{create_config_with_db_path, error, GetLogFilter, SharedParams, VersionInfo};
+use substrate_client::runtime_api::ProvideRuntimeApi;
+
+/// Custom subcommands of the node.
+#[derive(Debug, StructOpt, Clone)]
+pub enum CustomCommand {
+    /// Query the CNS registry in the node's database
+    #[structopt(name = "cns")]
+    Cns(CnsCmd),
+}
+
+impl GetLogFilter for CustomCommand {
+    fn get_log_filter(&self) -> Option<String> {
+        match self {
+            CustomCommand::Cns(cmd) => cmd.shared_params.log.clone(),
+        }
+    }
+}
+
+/// The `cns` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct CnsCmd {
+    #[structopt(subcommand)]
+    pub query: CnsQuery,
+
+    /// Print results as JSON instead of a table
+    #[structopt(long = "json")]
+    pub json: bool,
+
+    #[allow(missing_docs)]
+    #[structopt(flatten)]
+    pub shared_params: SharedParams,
+}
+
+/// Queries of the `cns` command, run against the best block.
+#[derive(Debug, StructOpt, Clone)]
+pub enum CnsQuery {
+    /// Show the account a name resolves to
+    #[structopt(name = "resolve")]
+    Resolve {
+        /// The name to resolve
+        name: String,
+    },
+    /// Show the registration of a name
+    #[structopt(name = "whois")]
+    Whois {
+        /// The name to look up
+        name: String,
+    },
+    /// List the names owned by an account
+    #[structopt(name = "list-owner")]
+    ListOwner {
+        /// The SS58 address of the owner
+        #[structopt(parse(try_from_str = "parse_account"))]
+        account: AccountId,
+        /// Position in the owner's names to start listing from
+        #[structopt(long = "start", default_value = "0")]
+        start: u64,
+        /// Maximum number of names to list
+        #[structopt(long = "count", default_value = "100")]
+        count: u64,
+    },
+    /// List the running auctions
+    #[structopt(name = "list-auctions")]
+    ListAuctions,
+    /// List the storefront items of a name
+    #[structopt(name = "list-items")]
+    ListItems {
+        /// The name to list items of
+        name: String,
+    },
+}
+
+fn parse_account(address: &str) -> Result<AccountId, String> {
+    AccountId::from_ss58check(address).map_err(|e| format!("Invalid address: {:?}", e))
+}
+
+/// Query results, one row per entry.
+struct Table {
+    headers: &'static [&'static str],
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn new(headers: &'static [&'static str]) -> Self {
+        Table {
+            headers,
+            rows: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    fn print(&self, json: bool) {
+        if json {
+            let rows: Vec<Value> = self
+                .rows
+                .iter()
+                .map(|row| {
+                    let object: Map<String, Value> = self
+                        .headers
+                        .iter()
+                        .zip(row)
+                        .map(|(header, cell)| (header.to_string(), Value::String(cell.clone())))
+                        .collect();
+                    Value::Object(object)
+                })
+                .collect();
+            println!("{}", Value::Array(rows));
+            return;
+        }
+
+        let widths: Vec<usize> = self
+            .headers
+            .iter()
+            .enumerate()
+            .map(|(i, header)| {
+                self.rows
+                    .iter()
+                    .map(|row| row[i].len())
+                    .max()
+                    .unwrap_or(0)
+                    .max(header.len())
+            })
+            .collect();
+        let line = |cells: Vec<&str>| {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            println!("{}", cells.join("  ").trim_end());
+        };
+        line(self.headers.to_vec());
+        for row in &self.rows {
+            line(row.iter().map(String::as_str).collect());
+        }
+    }
+}
+
+fn display_name(name: &[u8]) -> String {
+    String::from_utf8_lossy(name).into_owned()
+}
+
+/// A copy of a RocksDB database in a scratch directory next to it, removed on drop.
+struct Snapshot {
+    path: PathBuf,
+}
+
+impl Snapshot {
+    /// Snapshot the database at `source` like a RocksDB checkpoint: table files are immutable, so
+    /// they are hard linked, everything else is copied. The snapshot is created in the directory
+    /// holding the database, so that it is on the same filesystem and the tables are never copied.
+    fn new(source: &Path) -> error::Result<Self> {
+        if !source.join("CURRENT").is_file() {
+            return Err(format!("No database found at {}", source.display()).into());
+        }
+
+        let parent = source
+            .parent()
+            .ok_or_else(|| format!("No directory to snapshot {} in", source.display()))?;
+        let path = parent.join(format!("cns-db-{}", std::process::id()));
+        let io_error = |e: std::io::Error| format!("Cannot snapshot database: {}", e);
+        if path.exists() {
+            fs::remove_dir_all(&path).map_err(io_error)?;
+        }
+        let snapshot = Snapshot { path };
+        fs::create_dir_all(&snapshot.path).map_err(io_error)?;
+
+        for entry in fs::read_dir(source).map_err(io_error)? {
+            let entry = entry.map_err(io_error)?;
+            if !entry.file_type().map_err(io_error)?.is_file() {
+                continue;
+            }
+            let from = entry.path();
+            let to = snapshot.path.join(entry.file_name());
+            if from.extension().map_or(false, |ext| ext == "sst") {
+                fs::hard_link(&from, &to).map_err(io_error)?;
+            } else {
+                fs::copy(&from, &to).map_err(io_error)?;
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Run `cmd` against the database of the chain selected by its shared params. Fails if the chain
+/// has no database yet rather than creating one.
+pub fn run<S>(cmd: CnsCmd, spec_factory: S, version: &VersionInfo) -> error::Result<()>
+where
+    S: FnOnce(&str) -> Result<Option<chain_spec::ChainSpec>, String>,
+{
+    let mut config =
+        create_config_with_db_path::<Factory, _>(spec_factory, &cmd.shared_params, version)?;
+    // declared before the client so that it is removed after the client is closed
+    let snapshot = Snapshot::new(Path::new(&config.database_path))?;
+    config.database_path = snapshot.path.to_string_lossy().into();
+    let client =
+        substrate_service::new_client::<Factory>(&config).map_err(|e| format!("{:?}", e))?;
+    let at = BlockId::hash(
+        client
+            .info()
+            .map_err(|e| format!("{:?}", e))?
+            .chain
+            .best_hash,
+    );
+    let api = client.runtime_api();
+    let api_error = |e: substrate_client::error::Error| format!("{:?}", e);
+
+    let table = match cmd.query {
+        CnsQuery::Resolve { name } => {
+            let mut table = Table::new(&["name", "account"]);
+            if let Some(account) = api
+                .resolve(&at, name.clone().into_bytes())
+                .map_err(api_error)?
+            {
+                table.push(vec![name, account.to_string()]);
+            }
+            table
+        }
+        CnsQuery::Whois { name } => {
+            let name: DomainName = name.into_bytes();
+            let mut table = Table::new(&["name", "owner", "expire", "account", "parent"]);
+            if let Some(detail) = api.domain_detail(&at, name.clone()).map_err(api_error)? {
+                let account = api.resolve(&at, name.clone()).map_err(api_error)?;
+                table.push(vec![
+                    display_name(&name),
+                    detail.owner.to_string(),
+                    detail.expire.to_string(),
+                    account.map(|a| a.to_string()).unwrap_or_default(),
+                    detail.parent.map(|p| display_name(&p)).unwrap_or_default(),
+                ]);
+            }
+            table
+        }
+        CnsQuery::ListOwner {
+            account,
+            start,
+            count,
+        } => {
+            let mut table = Table::new(&["name", "expire"]);
+            for name in api
+                .names_of(&at, account, start, count)
+                .map_err(api_error)?
+            {
+                let expire = api
+                    .domain_detail(&at, name.clone())
+                    .map_err(api_error)?
+                    .map(|detail| detail.expire.to_string())
+                    .unwrap_or_default();
+                table.push(vec![display_name(&name), expire]);
+            }
+            table
+        }
+        CnsQuery::ListAuctions => {
+            let mut table = Table::new(&["name", "bidder", "amount", "end", "sealed"]);
+            for (name, info) in api.auctions(&at).map_err(api_error)? {
+                table.push(vec![
+                    display_name(&name),
+                    info.bid.bidder.to_string(),
+                    info.bid.amount.to_string(),
+                    info.end.to_string(),
+                    info.sealed.is_some().to_string(),
+                ]);
+            }
+            table
+        }
+        CnsQuery::ListItems { name } => {
+            let mut table = Table::new(&["item_id", "item", "quantity", "price_asset", "price"]);
+            for (item_id, item, quantity, (asset_id, price)) in
+                api.items_of(&at, name.into_bytes()).map_err(api_error)?
+            {
+                table.push(vec![
+                    item_id.to_string(),
+                    item.to_string(),
+                    quantity.to_string(),
+                    asset_id.to_string(),
+                    price.to_string(),
+                ]);
+            }
+            table
+        }
+    };
+
+    table.print(cmd.json);
+    Ok(())
+}
@@ -5,6 +5,8 @@
 
 mod chain_spec;
 mod cli;
+mod cns;
+mod rpc;
 mod service;
 
 pub use substrate_cli::{error, IntoExit, VersionInfo};
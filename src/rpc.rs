@@ -0,0 +1,188 @@
+//! The `cns` JSON-RPC namespace, backed by the CNS runtime APIs.
+//!
+//! It is served by its own HTTP server on full nodes, next to the stock Substrate RPC.
+
+use crate::service::Factory;
+use cennznet_runtime_template_runtime::{
+    opaque::BlockId, AccountId, AssetId, Balance, BidInfo, BlockNumber, DomainDetail,
+    DomainServiceApi, Hash, Item, ItemId, XPayApi,
+};
+use jsonrpc_core::{Error, ErrorCode, IoHandler, Result};
+use jsonrpc_derive::rpc;
+use jsonrpc_http_server::{AccessControlAllowOrigin, DomainsValidation, Server, ServerBuilder};
+use primitives::Bytes;
+use std::{io, net::SocketAddr, sync::Arc};
+use substrate_client::runtime_api::ProvideRuntimeApi;
+use substrate_service::FullClient;
+
+/// CNS name, auction and storefront queries. All methods take an optional block hash to query at,
+/// defaulting to the best block.
+#[rpc]
+pub trait CnsApi<BlockHash> {
+    /// The account a name resolves to.
+    #[rpc(name = "cns_resolve")]
+    fn resolve(&self, name: Bytes, at: Option<BlockHash>) -> Result<Option<AccountId>>;
+
+    /// The primary name of an account.
+    #[rpc(name = "cns_reverse")]
+    fn reverse(&self, account: AccountId, at: Option<BlockHash>) -> Result<Option<Bytes>>;
+
+    /// The registration of a name.
+    #[rpc(name = "cns_domain")]
+    fn domain(
+        &self,
+        name: Bytes,
+        at: Option<BlockHash>,
+    ) -> Result<Option<DomainDetail<AccountId, BlockNumber>>>;
+
+    /// Up to `count` names owned by an account, starting from position `start` of its owner index.
+    #[rpc(name = "cns_namesOf")]
+    fn names_of(
+        &self,
+        account: AccountId,
+        start: u64,
+        count: u64,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<Bytes>>;
+
+    /// The running auction for a name.
+    #[rpc(name = "cns_auction")]
+    fn auction(
+        &self,
+        name: Bytes,
+        at: Option<BlockHash>,
+    ) -> Result<Option<BidInfo<AccountId, Balance, BlockNumber>>>;
+
+    /// The storefront items of a name.
+    #[rpc(name = "cns_itemsOf")]
+    fn items_of(
+        &self,
+        name: Bytes,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<(ItemId, Item, u32, (AssetId, Balance))>>;
+}
+
+/// Implementation of `CnsApi` over a full client.
+pub struct Cns {
+    client: Arc<FullClient<Factory>>,
+}
+
+impl Cns {
+    /// Create a new `Cns` querying `client`.
+    pub fn new(client: Arc<FullClient<Factory>>) -> Self {
+        Cns { client }
+    }
+
+    fn block_id(&self, at: Option<Hash>) -> Result<BlockId> {
+        let hash = match at {
+            Some(hash) => hash,
+            None => self.client.info().map_err(client_error)?.chain.best_hash,
+        };
+        Ok(BlockId::hash(hash))
+    }
+}
+
+impl CnsApi<Hash> for Cns {
+    fn resolve(&self, name: Bytes, at: Option<Hash>) -> Result<Option<AccountId>> {
+        let at = self.block_id(at)?;
+        self.client
+            .runtime_api()
+            .resolve(&at, name.to_vec())
+            .map_err(client_error)
+    }
+
+    fn reverse(&self, account: AccountId, at: Option<Hash>) -> Result<Option<Bytes>> {
+        let at = self.block_id(at)?;
+        self.client
+            .runtime_api()
+            .reverse(&at, account)
+            .map(|name| name.map(Into::into))
+            .map_err(client_error)
+    }
+
+    fn domain(
+        &self,
+        name: Bytes,
+        at: Option<Hash>,
+    ) -> Result<Option<DomainDetail<AccountId, BlockNumber>>> {
+        let at = self.block_id(at)?;
+        self.client
+            .runtime_api()
+            .domain_detail(&at, name.to_vec())
+            .map_err(client_error)
+    }
+
+    fn names_of(
+        &self,
+        account: AccountId,
+        start: u64,
+        count: u64,
+        at: Option<Hash>,
+    ) -> Result<Vec<Bytes>> {
+        let at = self.block_id(at)?;
+        self.client
+            .runtime_api()
+            .names_of(&at, account, start, count)
+            .map(|names| names.into_iter().map(Into::into).collect())
+            .map_err(client_error)
+    }
+
+    fn auction(
+        &self,
+        name: Bytes,
+        at: Option<Hash>,
+    ) -> Result<Option<BidInfo<AccountId, Balance, BlockNumber>>> {
+        let at = self.block_id(at)?;
+        self.client
+            .runtime_api()
+            .bid_of(&at, name.to_vec())
+            .map_err(client_error)
+    }
+
+    fn items_of(
+        &self,
+        name: Bytes,
+        at: Option<Hash>,
+    ) -> Result<Vec<(ItemId, Item, u32, (AssetId, Balance))>> {
+        let at = self.block_id(at)?;
+        self.client
+            .runtime_api()
+            .items_of(&at, name.to_vec())
+            .map_err(client_error)
+    }
+}
+
+fn client_error(e: substrate_client::error::Error) -> Error {
+    Error {
+        code: ErrorCode::ServerError(1),
+        message: "Client error".into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}
+
+/// Start the `cns` RPC HTTP server on `addr`, accepting the origins in `cors` like the stock RPC
+/// server does (`None` accepts all). It stops when the returned server is dropped.
+pub fn start_http(
+    addr: &SocketAddr,
+    cors: Option<&Vec<String>>,
+    client: Arc<FullClient<Factory>>,
+) -> io::Result<Server> {
+    let mut io = IoHandler::new();
+    io.extend_with(CnsApi::to_delegate(Cns::new(client)));
+
+    ServerBuilder::new(io)
+        .threads(1)
+        .cors(map_cors(cors))
+        .start_http(addr)
+}
+
+fn map_cors(cors: Option<&Vec<String>>) -> DomainsValidation<AccessControlAllowOrigin> {
+    cors.map(|origins| {
+        origins
+            .iter()
+            .map(AsRef::as_ref)
+            .map(Into::into)
+            .collect::<Vec<_>>()
+    })
+    .into()
+}